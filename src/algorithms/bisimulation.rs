@@ -0,0 +1,153 @@
+use parsers::kripke_structure::MixedKripkeStructure;
+use std::collections::HashMap;
+use bit_set::BitSet;
+
+// The transitions of an LTS with states and actions numbered densely, which
+// is what partition refinement works on.
+pub struct Transitions {
+    pub states: Vec<usize>,
+    pub index: HashMap<usize, usize>,
+    pub actions: Vec<String>,
+    // (action, target) pairs for every state, by index
+    pub outgoing: Vec<Vec<(usize, usize)>>
+}
+
+impl Transitions {
+    pub fn new<L: Clone+Copy>(k: &MixedKripkeStructure<L>) -> Transitions {
+        let states = k.states.iter().collect::<Vec<usize>>();
+        let index = states.iter().enumerate().map(|(i, &s)| (s, i)).collect::<HashMap<usize, usize>>();
+        let actions = k.actions();
+        let action_index = actions.iter().enumerate().map(|(i, a)| (a.clone(), i)).collect::<HashMap<String, usize>>();
+        let mut outgoing = vec![vec!(); states.len()];
+        for (&(s, ref a), targets) in k.relations.iter() {
+            let i = index[&(s as usize)];
+            outgoing[i].extend(targets.iter().map(|t| (action_index[a], index[&t])));
+        }
+        for out in outgoing.iter_mut() {
+            out.sort();
+        }
+        Transitions { states: states, index: index, actions: actions, outgoing: outgoing }
+    }
+
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+}
+
+// A partition of the states of `Transitions` into blocks 0..count.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Partition {
+    pub block: Vec<usize>,
+    pub count: usize
+}
+
+impl Partition {
+    pub fn single(n: usize) -> Partition {
+        Partition { block: vec![0; n], count: if n == 0 { 0 } else { 1 } }
+    }
+
+    // Splits every block by the given signatures; blocks are numbered in
+    // order of their first state so results do not depend on hashing.
+    pub fn split<S: Eq + ::std::hash::Hash>(&self, signatures: Vec<S>) -> Partition {
+        let mut numbers: HashMap<(usize, S), usize> = HashMap::new();
+        let mut block = vec!();
+        for (i, signature) in signatures.into_iter().enumerate() {
+            let next = numbers.len();
+            block.push(*numbers.entry((self.block[i], signature)).or_insert(next));
+        }
+        Partition { block: block, count: numbers.len() }
+    }
+}
+
+// The signature of a state for strong bisimulation: the set of its
+// (action, target block) pairs.
+pub fn strong_signature(t: &Transitions, p: &Partition, i: usize) -> Vec<(usize, usize)> {
+    let mut signature = t.outgoing[i].iter().map(|&(a, j)| (a, p.block[j])).collect::<Vec<(usize, usize)>>();
+    signature.sort();
+    signature.dedup();
+    signature
+}
+
+// Signature-based partition refinement: blocks are split by signature until
+// the number of blocks no longer grows, which gives the coarsest strong
+// bisimulation.
+pub fn strong_bisimulation(t: &Transitions) -> Partition {
+    let mut partition = Partition::single(t.len());
+    loop {
+        let signatures = (0..t.len()).map(|i| strong_signature(t, &partition, i)).collect();
+        let next = partition.split(signatures);
+        if next.count == partition.count {
+            return next;
+        }
+        partition = next;
+    }
+}
+
+// The LTS with a state for every block and a transition between blocks for
+// every transition between their states.
+pub fn quotient<L: Clone+Copy>(k: &MixedKripkeStructure<L>, t: &Transitions, p: &Partition) -> MixedKripkeStructure<L> {
+    let mut states = BitSet::new();
+    for b in 0..p.count {
+        states.insert(b);
+    }
+    let mut init_states = BitSet::new();
+    for s in k.init_states.iter() {
+        init_states.insert(p.block[t.index[&s]]);
+    }
+    let mut relations: HashMap<(u32, String), BitSet> = HashMap::new();
+    for i in 0..t.len() {
+        for &(a, j) in &t.outgoing[i] {
+            relations.entry((p.block[i] as u32, t.actions[a].clone())).or_insert(BitSet::new()).insert(p.block[j]);
+        }
+    }
+    MixedKripkeStructure {
+        states: states,
+        init_states: init_states,
+        relations: relations,
+        label: HashMap::new()
+    }
+}
+
+// Minimises the LTS modulo strong bisimulation.
+pub fn reduce_strong<L: Clone+Copy>(k: &MixedKripkeStructure<L>) -> MixedKripkeStructure<L> {
+    let t = Transitions::new(k);
+    let partition = strong_bisimulation(&t);
+    quotient(k, &t, &partition)
+}
+
+#[cfg(test)]
+mod tests {
+    use algorithms::bisimulation::{Transitions, Partition, strong_bisimulation, quotient, reduce_strong};
+    use utils::random::lts;
+
+    fn related(t: &Transitions, p: &Partition, s: usize, u: usize) -> bool {
+        p.block[t.index[&s]] == p.block[t.index[&u]]
+    }
+
+    // a.b + a.b from 0, a.b from 5, a.b + a.c from 8 and a.(b + c) from 13
+    #[test]
+    fn known_answers() {
+        let k = lts(&[(0, "a", 1), (1, "b", 2), (0, "a", 3), (3, "b", 4), (5, "a", 6), (6, "b", 7),
+                      (8, "a", 9), (9, "b", 10), (8, "a", 11), (11, "c", 12), (13, "a", 14), (14, "b", 15), (14, "c", 16)]);
+        let t = Transitions::new(&k);
+        let p = strong_bisimulation(&t);
+        assert!(related(&t, &p, 0, 5));
+        assert!(!related(&t, &p, 8, 13));
+        assert!(!related(&t, &p, 0, 8));
+        // the deadlocks, the states doing only b, only c, or b and c, and the
+        // initial states of a.b, a.b + a.c and a.(b + c)
+        assert_eq!(p.count, 7);
+        let q = quotient(&k, &t, &p);
+        assert_eq!(q.states.len(), 7);
+        assert_eq!(q.init_states.iter().collect::<Vec<usize>>(), vec!(p.block[t.index[&0]]));
+    }
+
+    #[test]
+    fn reduction_merges_copies() {
+        // two copies of a loop a.b.a.b...
+        let k = lts(&[(0, "a", 1), (1, "b", 2), (2, "a", 3), (3, "b", 0)]);
+        let reduced = reduce_strong(&k);
+        assert_eq!(reduced.states.len(), 2);
+        assert_eq!(reduced.relations.values().map(|t| t.len()).sum::<usize>(), 2);
+    }
+}
@@ -0,0 +1,172 @@
+use parsers::kripke_structure::MixedKripkeStructure;
+use algorithms::bisimulation::{Transitions, Partition, quotient};
+use utils::graph::strongly_connected_components;
+use bit_set::BitSet;
+
+// Stands for every internal action in signatures, and for divergence.
+const INTERNAL: usize = ::std::usize::MAX;
+const DIVERGENT: usize = ::std::usize::MAX - 1;
+
+fn internal_indices(t: &Transitions, internal: &[String]) -> BitSet {
+    t.actions.iter().enumerate().filter(|&(_, a)| internal.contains(a)).map(|(i, _)| i).collect()
+}
+
+// Signatures for branching bisimulation: the (action, block) pairs a state
+// can do after inert internal steps, i.e. steps that stay inside its block,
+// leaving out those inert steps themselves. Inert steps form an acyclic graph
+// up to cycles within a block, so the signatures are collected per strongly
+// connected component in reverse topological order. Returns whether each
+// state can diverge along inert steps as well.
+fn signatures(t: &Transitions, p: &Partition, internal: &BitSet) -> (Vec<Vec<(usize, usize)>>, Vec<bool>) {
+    let inert = (0..t.len()).map(|i| {
+        t.outgoing[i].iter()
+            .filter(|&&(a, j)| internal.contains(a) && p.block[i] == p.block[j])
+            .map(|&(_, j)| j)
+            .collect::<Vec<usize>>()
+    }).collect::<Vec<Vec<usize>>>();
+    let components = strongly_connected_components(&inert);
+    let mut component_of = vec![0; t.len()];
+    for (c, component) in components.iter().enumerate() {
+        for &i in component {
+            component_of[i] = c;
+        }
+    }
+    let mut component_signatures: Vec<Vec<(usize, usize)>> = vec!();
+    let mut component_divergent = vec!();
+    for (c, component) in components.iter().enumerate() {
+        let mut signature = vec!();
+        let mut divergent = component.len() > 1;
+        for &i in component {
+            for &(a, j) in &t.outgoing[i] {
+                let action = if internal.contains(a) { INTERNAL } else { a };
+                if action != INTERNAL || p.block[i] != p.block[j] {
+                    signature.push((action, p.block[j]));
+                }
+            }
+            for &j in &inert[i] {
+                let d = component_of[j];
+                if d == c {
+                    divergent = true;
+                } else {
+                    signature.extend(component_signatures[d].iter().cloned());
+                    divergent = divergent || component_divergent[d];
+                }
+            }
+        }
+        signature.sort();
+        signature.dedup();
+        component_signatures.push(signature);
+        component_divergent.push(divergent);
+    }
+    let signatures = (0..t.len()).map(|i| component_signatures[component_of[i]].clone()).collect();
+    let divergent = (0..t.len()).map(|i| component_divergent[component_of[i]]).collect();
+    (signatures, divergent)
+}
+
+// The coarsest branching bisimulation, divergence-preserving when asked,
+// by signature refinement.
+pub fn branching_bisimulation(t: &Transitions, internal: &[String], divergence: bool) -> Partition {
+    let internal = internal_indices(t, internal);
+    let mut partition = Partition::single(t.len());
+    loop {
+        let (mut signatures, divergent) = signatures(t, &partition, &internal);
+        if divergence {
+            for i in 0..t.len() {
+                if divergent[i] {
+                    signatures[i].push((DIVERGENT, 0));
+                }
+            }
+        }
+        let next = partition.split(signatures);
+        if next.count == partition.count {
+            return next;
+        }
+        partition = next;
+    }
+}
+
+// Minimises the LTS modulo (divergence-preserving) branching bisimulation.
+// Internal steps inside a block disappear, except that divergent blocks keep
+// an internal self loop when divergence is preserved.
+pub fn reduce_branching<L: Clone+Copy>(k: &MixedKripkeStructure<L>, internal: &[String], divergence: bool) -> MixedKripkeStructure<L> {
+    let t = Transitions::new(k);
+    let partition = branching_bisimulation(&t, internal, divergence);
+    let mut reduced = quotient(k, &t, &partition);
+    let divergent = signatures(&t, &partition, &internal_indices(&t, internal)).1;
+    let mut keep = BitSet::new();
+    if divergence {
+        for i in 0..t.len() {
+            if divergent[i] {
+                keep.insert(partition.block[i]);
+            }
+        }
+    }
+    for (&(b, ref a), targets) in reduced.relations.iter_mut() {
+        if internal.contains(a) && !keep.contains(b as usize) {
+            targets.remove(b as usize);
+        }
+    }
+    let empty = reduced.relations.iter().filter(|&(_, targets)| targets.is_empty()).map(|(key, _)| key.clone()).collect::<Vec<(u32, String)>>();
+    for key in empty {
+        reduced.relations.remove(&key);
+    }
+    reduced
+}
+
+#[cfg(test)]
+mod tests {
+    use algorithms::bisimulation::Transitions;
+    use algorithms::branching::{branching_bisimulation, reduce_branching};
+    use parsers::kripke_structure::MixedKripkeStructure;
+    use utils::random::lts;
+
+    fn related(k: &MixedKripkeStructure<()>, s: usize, u: usize, divergence: bool) -> bool {
+        let t = Transitions::new(k);
+        let p = branching_bisimulation(&t, &[String::from("tau")], divergence);
+        p.block[t.index[&s]] == p.block[t.index[&u]]
+    }
+
+    // a.tau.b + a.b from 0 and a.b from 6
+    #[test]
+    fn inert_steps_are_skipped() {
+        let k = lts(&[(0, "a", 1), (1, "tau", 2), (2, "b", 3), (0, "a", 4), (4, "b", 5), (6, "a", 7), (7, "b", 8)]);
+        assert!(related(&k, 0, 6, false));
+        assert!(related(&k, 0, 6, true));
+        assert!(related(&k, 1, 7, false));
+    }
+
+    // a.(tau.b + c) from 0 and a.(b + c) from 5: the internal step gives up c
+    #[test]
+    fn internal_steps_that_lose_options_are_kept() {
+        let k = lts(&[(0, "a", 1), (1, "tau", 2), (2, "b", 3), (1, "c", 4), (5, "a", 6), (6, "b", 7), (6, "c", 8)]);
+        assert!(!related(&k, 0, 5, false));
+        assert!(!related(&k, 1, 6, false));
+    }
+
+    // an internal loop before a from 0 and just a from 2
+    #[test]
+    fn divergence_is_only_observed_when_preserved() {
+        let k = lts(&[(0, "tau", 0), (0, "a", 1), (2, "a", 3)]);
+        assert!(related(&k, 0, 2, false));
+        assert!(!related(&k, 0, 2, true));
+        // a cycle of two internal steps diverges as well
+        let k = lts(&[(0, "tau", 1), (1, "tau", 0), (0, "a", 2), (1, "a", 2), (3, "a", 4)]);
+        assert!(related(&k, 0, 3, false));
+        assert!(!related(&k, 0, 3, true));
+    }
+
+    #[test]
+    fn reduction_drops_inert_steps() {
+        let tau = String::from("tau");
+        let k = lts(&[(0, "tau", 0), (0, "a", 1), (0, "tau", 2), (2, "a", 1)]);
+        let reduced = reduce_branching(&k, &[tau.clone()], false);
+        assert_eq!(reduced.states.len(), 2);
+        assert!(reduced.relations.keys().all(|&(_, ref a)| a != &tau));
+        // 2 cannot diverge, so the step from 0 to 2 is no longer inert
+        let divergent = reduce_branching(&k, &[tau.clone()], true);
+        assert_eq!(divergent.states.len(), 3);
+        let init = divergent.init_states.iter().next().unwrap();
+        assert_eq!(divergent.relations[&(init as u32, tau.clone())].len(), 2);
+        assert!(divergent.relations[&(init as u32, tau.clone())].contains(init));
+    }
+}
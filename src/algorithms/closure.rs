@@ -0,0 +1,150 @@
+use parsers::mucalculus::{MuFormula, weak_actions};
+use parsers::kripke_structure::MixedKripkeStructure;
+use std::collections::{HashMap, HashSet, VecDeque};
+use bit_set::BitSet;
+
+// For every state, the states reachable by zero or more internal steps.
+pub fn tau_closure<L: Clone+Copy>(k: &MixedKripkeStructure<L>, internal: &[String]) -> HashMap<usize, BitSet> {
+    let mut closure = HashMap::new();
+    for s in k.states.iter() {
+        let mut reached = BitSet::new();
+        reached.insert(s);
+        let mut stack = vec![s];
+        while let Some(u) = stack.pop() {
+            for a in internal {
+                if let Some(targets) = k.relations.get(&(u as u32, a.clone())) {
+                    for t in targets.iter() {
+                        if reached.insert(t) {
+                            stack.push(t);
+                        }
+                    }
+                }
+            }
+        }
+        closure.insert(s, reached);
+    }
+    closure
+}
+
+// The states reachable from `from` by internal steps, or by internal steps
+// around a single `a` step when an action is given.
+pub fn weak_successors<L: Clone+Copy>(k: &MixedKripkeStructure<L>, closure: &HashMap<usize, BitSet>, from: &BitSet, action: Option<&String>) -> BitSet {
+    let mut before = BitSet::new();
+    for s in from.iter() {
+        if let Some(c) = closure.get(&s) {
+            before.union_with(c);
+        }
+    }
+    let action = match action {
+        Some(a) => a,
+        None => return before
+    };
+    let mut result = BitSet::new();
+    for s in before.iter() {
+        if let Some(targets) = k.relations.get(&(s as u32, action.clone())) {
+            for t in targets.iter() {
+                if let Some(c) = closure.get(&t) {
+                    result.union_with(c);
+                }
+            }
+        }
+    }
+    result
+}
+
+// The LTS with a transition s -a-> t for every weak step s =a=> t, and an
+// internal transition s -> t for every sequence of zero or more internal
+// steps, labelled with the first internal action. Strong bisimulation on it
+// is weak bisimulation on the original.
+pub fn saturate<L: Clone+Copy>(k: &MixedKripkeStructure<L>, internal: &[String]) -> MixedKripkeStructure<L> {
+    let closure = tau_closure(k, internal);
+    let visible = k.actions().into_iter().filter(|a| !internal.contains(a)).collect::<Vec<String>>();
+    let tau = internal.first().cloned().unwrap_or(String::from("tau"));
+    let mut relations = HashMap::new();
+    for s in k.states.iter() {
+        relations.insert((s as u32, tau.clone()), closure[&s].clone());
+        let mut from = BitSet::new();
+        from.insert(s);
+        for a in &visible {
+            let targets = weak_successors(k, &closure, &from, Some(a));
+            if !targets.is_empty() {
+                relations.insert((s as u32, a.clone()), targets);
+            }
+        }
+    }
+    MixedKripkeStructure {
+        states: k.states.clone(),
+        init_states: k.init_states.clone(),
+        relations: relations,
+        label: HashMap::new()
+    }
+}
+
+// The weak steps s =a=> t for the actions of the weak modalities in `mu`,
+// which every evaluator uses in place of the transitions for <<a>> and [[a]].
+// A weak step is any number of steps by the `internal` actions before and
+// after an a-step; for an internal action a it is zero or more internal
+// steps. Empty, and without computing the closure, when there are none.
+pub fn weak_steps<L: Clone+Copy>(k: &MixedKripkeStructure<L>, mu: &MuFormula, internal: &[String]) -> HashMap<(u32, String), BitSet> {
+    let actions = weak_actions(mu);
+    let mut steps = HashMap::new();
+    if actions.is_empty() {
+        return steps;
+    }
+    let closure = tau_closure(k, internal);
+    for s in k.states.iter() {
+        let mut from = BitSet::new();
+        from.insert(s);
+        for a in &actions {
+            let targets = if internal.contains(a) {
+                weak_successors(k, &closure, &from, None)
+            } else {
+                weak_successors(k, &closure, &from, Some(a))
+            };
+            if !targets.is_empty() {
+                steps.insert((s as u32, a.clone()), targets);
+            }
+        }
+    }
+    steps
+}
+
+// A shortest sequence of transitions from `from` to `to` that makes up a
+// weak step by `action`.
+pub fn weak_path<L: Clone+Copy>(k: &MixedKripkeStructure<L>, from: u32, action: &String, to: u32, internal: &[String]) -> Option<Vec<(u32, String, u32)>> {
+    // states paired with whether the step by `action` has been taken
+    let start = (from as usize, internal.contains(action));
+    let mut parent: HashMap<(usize, bool), ((usize, bool), String)> = HashMap::new();
+    let mut visited = HashSet::new();
+    visited.insert(start);
+    let mut queue = VecDeque::new();
+    queue.push_back(start);
+    while let Some(current) = queue.pop_front() {
+        if current == (to as usize, true) {
+            let mut path = vec!();
+            let mut v = current;
+            while let Some(&(previous, ref a)) = parent.get(&v) {
+                path.push((previous.0 as u32, a.clone(), v.0 as u32));
+                v = previous;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        let (s, taken) = current;
+        let mut moves = internal.iter().map(|a| (a, taken)).collect::<Vec<(&String, bool)>>();
+        if !taken {
+            moves.push((action, true));
+        }
+        for (a, taken) in moves {
+            if let Some(targets) = k.relations.get(&(s as u32, a.clone())) {
+                for t in targets.iter() {
+                    if visited.insert((t, taken)) {
+                        parent.insert((t, taken), (current, a.clone()));
+                        queue.push_back((t, taken));
+                    }
+                }
+            }
+        }
+    }
+    None
+}
@@ -0,0 +1,180 @@
+use parsers::kripke_structure::MixedKripkeStructure;
+use std::collections::{HashMap, VecDeque};
+use bit_set::BitSet;
+
+// How the components of a parallel composition interact.
+#[derive(Clone, Debug)]
+pub enum Synchronisation {
+    // CSP style: an action in the alphabet of several components can only be
+    // done by all of them together; internal actions never synchronise.
+    SharedAlphabet,
+    // mCRL2 style: for every (parts, result), components doing the parts at
+    // the same time together do the result. All actions, the parts
+    // included, can also be done by a single component; block the parts to
+    // enforce communication.
+    Communication(Vec<(Vec<String>, String)>)
+}
+
+fn successors<L: Clone+Copy>(k: &MixedKripkeStructure<L>, s: usize, a: &String) -> Vec<usize> {
+    k.relations.get(&(s as u32, a.clone())).map_or(vec!(), |targets| targets.iter().collect())
+}
+
+// All ways of picking a successor in each of `choices`.
+fn combinations(choices: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let mut result = vec![vec!()];
+    for options in choices {
+        result = result.into_iter().flat_map(|prefix: Vec<usize>| {
+            options.iter().map(move |&o| {
+                let mut next = prefix.clone();
+                next.push(o);
+                next
+            })
+        }).collect();
+    }
+    result
+}
+
+// All injective assignments of `parts` to components (as index lists).
+fn assignments(parts: usize, components: usize) -> Vec<Vec<usize>> {
+    let mut result = vec![vec!()];
+    for _ in 0..parts {
+        result = result.into_iter().flat_map(|prefix: Vec<usize>| {
+            (0..components).filter(|c| !prefix.contains(c)).map(|c| {
+                let mut next = prefix.clone();
+                next.push(c);
+                next
+            }).collect::<Vec<Vec<usize>>>()
+        }).collect();
+    }
+    result
+}
+
+// a step of component i on its own
+fn independent<L: Clone+Copy>(components: &[&MixedKripkeStructure<L>], state: &[usize], i: usize, a: &String, result: &mut Vec<(String, Vec<usize>, Vec<usize>)>) {
+    for t in successors(components[i], state[i], a) {
+        result.push((a.clone(), vec![i], vec![t]));
+    }
+}
+
+// The steps of the product from `state`: the label, the components that
+// move, and their targets.
+fn steps<L: Clone+Copy>(components: &[&MixedKripkeStructure<L>], alphabets: &[Vec<String>], state: &[usize], synchronisation: &Synchronisation, internal: &[String]) -> Vec<(String, Vec<usize>, Vec<usize>)> {
+    let mut result = vec!();
+    match *synchronisation {
+        Synchronisation::SharedAlphabet => {
+            let mut actions = alphabets.iter().flat_map(|alphabet| alphabet.iter().cloned()).collect::<Vec<String>>();
+            actions.sort();
+            actions.dedup();
+            for a in actions {
+                let participants = (0..components.len()).filter(|&i| alphabets[i].contains(&a)).collect::<Vec<usize>>();
+                if internal.contains(&a) {
+                    for &i in &participants {
+                        independent(components, state, i, &a, &mut result);
+                    }
+                    continue;
+                }
+                let choices = participants.iter().map(|&i| successors(components[i], state[i], &a)).collect::<Vec<Vec<usize>>>();
+                for targets in combinations(&choices) {
+                    result.push((a.clone(), participants.clone(), targets));
+                }
+            }
+        }
+        Synchronisation::Communication(ref rules) => {
+            for i in 0..components.len() {
+                for a in &alphabets[i] {
+                    independent(components, state, i, a, &mut result);
+                }
+            }
+            for &(ref parts, ref c) in rules {
+                for assignment in assignments(parts.len(), components.len()) {
+                    let choices = assignment.iter().zip(parts.iter()).map(|(&i, a)| successors(components[i], state[i], a)).collect::<Vec<Vec<usize>>>();
+                    for targets in combinations(&choices) {
+                        result.push((c.clone(), assignment.clone(), targets));
+                    }
+                }
+            }
+        }
+    }
+    result
+}
+
+// The parallel composition of the components, restricted to the product
+// states reachable from the tuple of initial states. Product states are
+// numbered in the order they are found, starting at 0.
+pub fn compose<L: Clone+Copy>(components: &[&MixedKripkeStructure<L>], synchronisation: &Synchronisation, internal: &[String]) -> MixedKripkeStructure<()> {
+    let alphabets = components.iter().map(|k| k.actions()).collect::<Vec<Vec<String>>>();
+    let initial = components.iter().map(|k| k.init_states.iter().next().unwrap_or(0)).collect::<Vec<usize>>();
+    let mut ids: HashMap<Vec<usize>, usize> = HashMap::new();
+    ids.insert(initial.clone(), 0);
+    let mut queue = VecDeque::new();
+    queue.push_back(initial);
+    let mut relations: HashMap<(u32, String), BitSet> = HashMap::new();
+    while let Some(state) = queue.pop_front() {
+        let id = ids[&state];
+        for (a, moving, targets) in steps(components, &alphabets, &state, synchronisation, internal) {
+            let mut next = state.clone();
+            for (&i, &t) in moving.iter().zip(targets.iter()) {
+                next[i] = t;
+            }
+            let fresh = ids.len();
+            let target = *ids.entry(next.clone()).or_insert(fresh);
+            if target == fresh {
+                queue.push_back(next);
+            }
+            relations.entry((id as u32, a)).or_insert(BitSet::new()).insert(target);
+        }
+    }
+    let mut init_states = BitSet::new();
+    init_states.insert(0);
+    MixedKripkeStructure {
+        states: (0..ids.len()).collect(),
+        init_states: init_states,
+        relations: relations,
+        label: HashMap::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use algorithms::composition::{compose, assignments, combinations, Synchronisation};
+    use parsers::kripke_structure::MixedKripkeStructure;
+    use utils::random::lts;
+
+    fn transitions(k: &MixedKripkeStructure<()>) -> usize {
+        k.relations.values().map(|t| t.len()).sum()
+    }
+
+    #[test]
+    fn choices() {
+        assert_eq!(combinations(&[vec!(1, 2), vec!(3)]), vec!(vec!(1, 3), vec!(2, 3)));
+        assert!(combinations(&[vec!(1, 2), vec!()]).is_empty());
+        assert_eq!(assignments(2, 3), vec!(vec!(0, 1), vec!(0, 2), vec!(1, 0), vec!(1, 2), vec!(2, 0), vec!(2, 1)));
+        assert!(assignments(3, 2).is_empty());
+    }
+
+    // a is shared and synchronises, b and c interleave, and the internal tau
+    // of the first component never waits for the second
+    #[test]
+    fn shared_alphabet() {
+        let p = lts(&[(0, "a", 1), (1, "b", 0), (1, "tau", 1)]);
+        let q = lts(&[(0, "a", 1), (1, "c", 0), (1, "tau", 0)]);
+        let composed = compose(&[&p, &q], &Synchronisation::SharedAlphabet, &[String::from("tau")]);
+        assert_eq!(composed.states.len(), 4);
+        assert_eq!(transitions(&composed), 9);
+        assert_eq!(composed.relations.keys().filter(|&&(_, ref a)| a == "a").count(), 1);
+    }
+
+    // s and r may happen on their own or together as c
+    #[test]
+    fn communication() {
+        let p = lts(&[(0, "s", 1)]);
+        let q = lts(&[(0, "r", 1)]);
+        let rules = vec!((vec!(String::from("s"), String::from("r")), String::from("c")));
+        let composed = compose(&[&p, &q], &Synchronisation::Communication(rules), &[]);
+        assert_eq!(composed.states.len(), 4);
+        assert_eq!(transitions(&composed), 5);
+        let together = composed.relations.get(&(0, String::from("c"))).unwrap();
+        assert_eq!(together.len(), 1);
+        assert!(composed.relations.get(&(together.iter().next().unwrap() as u32, String::from("s"))).is_none());
+    }
+}
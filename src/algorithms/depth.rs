@@ -8,7 +8,8 @@ pub fn nesting_depth(mu: &MuFormula) -> u64 {
             return 0;
         }
 
-        MuFormula::Not(_, ref f) | MuFormula::DiamondOp (_,  _, ref f) | MuFormula::BoxOp (_,  _, ref f)  => {
+        MuFormula::Not(_, ref f) | MuFormula::DiamondOp (_,  _, ref f) | MuFormula::BoxOp (_,  _, ref f) |
+        MuFormula::WeakDiamondOp (_,  _, ref f) | MuFormula::WeakBoxOp (_,  _, ref f) => {
             return nesting_depth(&f);
         }
 
@@ -29,7 +30,8 @@ pub fn alternation_depth(mu: &MuFormula) -> u64 {
             return 0;
         }
 
-        MuFormula::Not(_, ref f) | MuFormula::DiamondOp (_,  _, ref f) | MuFormula::BoxOp (_,  _, ref f)  => {
+        MuFormula::Not(_, ref f) | MuFormula::DiamondOp (_,  _, ref f) | MuFormula::BoxOp (_,  _, ref f) |
+        MuFormula::WeakDiamondOp (_,  _, ref f) | MuFormula::WeakBoxOp (_,  _, ref f) => {
             return alternation_depth(&f);
         }
 
@@ -79,7 +81,8 @@ pub fn dependent_alternation_depth(mu: &MuFormula) -> u64 {
             return 0;
         }
 
-        MuFormula::Not(_, ref f) | MuFormula::DiamondOp (_,  _, ref f) | MuFormula::BoxOp (_,  _, ref f)  => {
+        MuFormula::Not(_, ref f) | MuFormula::DiamondOp (_,  _, ref f) | MuFormula::BoxOp (_,  _, ref f) |
+        MuFormula::WeakDiamondOp (_,  _, ref f) | MuFormula::WeakBoxOp (_,  _, ref f) => {
             return dependent_alternation_depth(&f);
         }
 
@@ -0,0 +1,88 @@
+use parsers::kripke_structure::MixedKripkeStructure;
+use algorithms::closure::{tau_closure, weak_successors};
+use algorithms::bisimulation::reduce_strong;
+use std::collections::{HashMap, VecDeque};
+use bit_set::BitSet;
+
+// The deterministic LTS with the same traces, by the subset construction.
+// Each state is the set of states reachable by a trace, closed under internal
+// steps, so internal actions disappear; only the subsets reachable from the
+// initial states are built, numbered from 0 in the order they are found, and
+// the empty subset is left out.
+pub fn determinise<L: Clone+Copy>(k: &MixedKripkeStructure<L>, internal: &[String]) -> MixedKripkeStructure<()> {
+    let closure = tau_closure(k, internal);
+    let visible = k.actions().into_iter().filter(|a| !internal.contains(a)).collect::<Vec<String>>();
+
+    let start = weak_successors(k, &closure, &k.init_states, None);
+    let mut ids = HashMap::new();
+    ids.insert(start.clone(), 0);
+    let mut queue = VecDeque::new();
+    queue.push_back(start);
+    let mut relations = HashMap::new();
+    while let Some(subset) = queue.pop_front() {
+        let id = ids[&subset];
+        for a in &visible {
+            let next = weak_successors(k, &closure, &subset, Some(a));
+            if next.is_empty() {
+                continue;
+            }
+            let fresh = ids.len();
+            let target = *ids.entry(next.clone()).or_insert(fresh);
+            if target == fresh {
+                queue.push_back(next);
+            }
+            relations.entry((id as u32, a.clone())).or_insert(BitSet::new()).insert(target);
+        }
+    }
+    let mut init_states = BitSet::new();
+    init_states.insert(0);
+    MixedKripkeStructure {
+        states: (0..ids.len()).collect(),
+        init_states: init_states,
+        relations: relations,
+        label: HashMap::new()
+    }
+}
+
+// The least deterministic LTS with the same traces. Every state of a
+// deterministic LTS accepts, so minimising it as an automaton is minimising
+// it modulo strong bisimulation.
+pub fn reduce_trace<L: Clone+Copy>(k: &MixedKripkeStructure<L>, internal: &[String]) -> MixedKripkeStructure<()> {
+    reduce_strong(&determinise(k, internal))
+}
+
+#[cfg(test)]
+mod tests {
+    use algorithms::determinise::{determinise, reduce_trace};
+    use algorithms::equivalence::{compare, Equivalence};
+    use utils::random::{Random, random_kripke, lts};
+
+    #[test]
+    fn subsets_and_hidden_steps() {
+        let tau = vec!(String::from("tau"));
+        // tau.a.b + a.c becomes a.(b + c)
+        let k = lts(&[(0, "tau", 1), (1, "a", 2), (2, "b", 3), (0, "a", 4), (4, "c", 5)]);
+        let d = determinise(&k, &tau);
+        assert_eq!(d.states.len(), 4);
+        assert!(d.relations.keys().all(|&(_, ref a)| a != "tau"));
+        assert!(d.relations.values().all(|targets| targets.len() == 1));
+        // the states after b and after c are the same deadlock
+        assert_eq!(reduce_trace(&k, &tau).states.len(), 3);
+    }
+
+    #[test]
+    fn reduction_keeps_traces() {
+        let tau = vec!(String::from("tau"));
+        let actions = vec!(String::from("a"), String::from("b"), String::from("tau"));
+        for seed in 1..51 {
+            let mut rng = Random::new(seed);
+            let states = 1 + rng.below(8);
+            let transitions = rng.below(3 * states);
+            let k = random_kripke(&mut rng, states, transitions, &actions);
+            let reduced = reduce_trace(&k, &tau);
+            assert!(reduced.relations.values().all(|targets| targets.len() == 1), "seed {}", seed);
+            assert!(compare(&k, &reduced, Equivalence::Trace, &tau).equivalent, "seed {}", seed);
+            assert!(reduced.states.len() <= determinise(&k, &tau).states.len());
+        }
+    }
+}
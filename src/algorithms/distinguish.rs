@@ -0,0 +1,140 @@
+use parsers::mucalculus::{MuFormula, renumber};
+use algorithms::bisimulation::{Transitions, Partition, strong_signature};
+use std::collections::HashMap;
+
+// All partitions computed by strong bisimulation refinement, starting with
+// the single block and ending with the coarsest bisimulation.
+pub fn refinement_history(t: &Transitions) -> Vec<Partition> {
+    let mut history = vec![Partition::single(t.len())];
+    loop {
+        let next = {
+            let partition = history.last().unwrap();
+            let signatures = (0..t.len()).map(|i| strong_signature(t, partition, i)).collect();
+            partition.split(signatures)
+        };
+        if next.count == history.last().unwrap().count {
+            return history;
+        }
+        history.push(next);
+    }
+}
+
+struct Distinguisher<'a> {
+    t: &'a Transitions,
+    history: &'a Vec<Partition>,
+    cache: HashMap<(usize, usize), MuFormula>
+}
+
+impl<'a> Distinguisher<'a> {
+    // the first round in which s and u are in different blocks
+    fn round(&self, s: usize, u: usize) -> usize {
+        (0..self.history.len()).find(|&r| self.history[r].block[s] != self.history[r].block[u]).unwrap()
+    }
+
+    // (action, block) pairs of s in round r that u does not have
+    fn missing(&self, s: usize, u: usize, r: usize) -> Vec<(usize, usize)> {
+        let other = strong_signature(self.t, &self.history[r], u);
+        strong_signature(self.t, &self.history[r], s).into_iter().filter(|pair| !other.contains(pair)).collect()
+    }
+
+    // A formula that holds in s but not in u. They were split in some round
+    // because one of them can do an action into a block of the previous
+    // round that the other cannot; that step becomes a diamond for s, or a
+    // box for u, over formulas separating the successors, which were split
+    // in an earlier round.
+    fn formula(&mut self, s: usize, u: usize) -> MuFormula {
+        if let Some(f) = self.cache.get(&(s, u)) {
+            return f.clone();
+        }
+        let r = self.round(s, u) - 1;
+        let result = match self.missing(s, u, r).first().cloned() {
+            Some((a, block)) => {
+                // <a> of the conjunction separating a successor of s in
+                // `block` from every a-successor of u
+                let next = self.t.outgoing[s].iter().find(|&&(b, j)| b == a && self.history[r].block[j] == block).unwrap().1;
+                let others = self.successors(u, a);
+                let parts = others.into_iter().map(|j| self.formula(next, j)).collect::<Vec<MuFormula>>();
+                MuFormula::DiamondOp(0, self.t.actions[a].clone(), box conjunction(parts))
+            }
+            None => {
+                // u can do a step into a block that s cannot reach; [a] of
+                // the disjunction separating every a-successor of s from it
+                let (a, block) = self.missing(u, s, r)[0];
+                let next = self.t.outgoing[u].iter().find(|&&(b, j)| b == a && self.history[r].block[j] == block).unwrap().1;
+                let others = self.successors(s, a);
+                let parts = others.into_iter().map(|j| self.formula(j, next)).collect::<Vec<MuFormula>>();
+                MuFormula::BoxOp(0, self.t.actions[a].clone(), box disjunction(parts))
+            }
+        };
+        self.cache.insert((s, u), result.clone());
+        result
+    }
+
+    fn successors(&self, s: usize, a: usize) -> Vec<usize> {
+        let mut result = self.t.outgoing[s].iter().filter(|&&(b, _)| b == a).map(|&(_, j)| j).collect::<Vec<usize>>();
+        result.dedup();
+        result
+    }
+}
+
+fn conjunction(parts: Vec<MuFormula>) -> MuFormula {
+    parts.into_iter().fold(None, |result, f| Some(match result {
+        Some(g) => MuFormula::And(0, box g, box f),
+        None => f
+    })).unwrap_or(MuFormula::Bool(0, true))
+}
+
+fn disjunction(parts: Vec<MuFormula>) -> MuFormula {
+    parts.into_iter().fold(None, |result, f| Some(match result {
+        Some(g) => MuFormula::Or(0, box g, box f),
+        None => f
+    })).unwrap_or(MuFormula::Bool(0, false))
+}
+
+// A Hennessy-Milner formula that holds in state s and not in state u (given
+// as states of the LTS, not indices), or None when they are bisimilar.
+pub fn distinguishing_formula(t: &Transitions, history: &Vec<Partition>, s: usize, u: usize) -> Option<MuFormula> {
+    let (i, j) = (t.index[&s], t.index[&u]);
+    if history.last().unwrap().block[i] == history.last().unwrap().block[j] {
+        return None;
+    }
+    let mut distinguisher = Distinguisher { t: t, history: history, cache: HashMap::new() };
+    Some(renumber(&distinguisher.formula(i, j)))
+}
+
+#[cfg(test)]
+mod tests {
+    use algorithms::bisimulation::{Transitions, strong_bisimulation};
+    use algorithms::distinguish::{refinement_history, distinguishing_formula};
+    use algorithms::emerson_lei;
+    use utils::random::{Random, random_kripke};
+
+    // Every formula is re-checked: it must hold in the first state and fail
+    // in the second, and bisimilar states must get none.
+    #[test]
+    fn formulas_distinguish() {
+        let actions = vec!(String::from("a"), String::from("b"));
+        for seed in 1..21 {
+            let mut rng = Random::new(seed);
+            let states = 2 + rng.below(8);
+            let transitions = rng.below(3 * states);
+            let k = random_kripke(&mut rng, states, transitions, &actions);
+            let t = Transitions::new(&k);
+            let history = refinement_history(&t);
+            assert_eq!(history.last().unwrap(), &strong_bisimulation(&t));
+            for s in k.states.iter() {
+                for u in k.states.iter() {
+                    let bisimilar = history.last().unwrap().block[t.index[&s]] == history.last().unwrap().block[t.index[&u]];
+                    match distinguishing_formula(&t, &history, s, u) {
+                        Some(f) => {
+                            assert!(!bisimilar, "{} and {} are bisimilar but told apart by {}", s, u, f.to_string());
+                            let holds = emerson_lei::evaluate(&k, f.clone(), &[]).unwrap();
+                            assert!(holds.contains(s) && !holds.contains(u), "{} does not tell {} from {}", f.to_string(), s, u);
+                        }
+                        None => assert!(bisimilar, "no formula for {} and {}", s, u)
+                    }
+                }
+            }
+        }
+    }
+}
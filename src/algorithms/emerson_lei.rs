@@ -1,134 +1,112 @@
-use parsers::mucalculus::{MuFormula, find_children};
+use parsers::mucalculus::MuFormula;
 use parsers::kripke_structure::MixedKripkeStructure;
+use algorithms::closure::weak_steps;
 use std::collections::HashSet;
 use std::collections::HashMap;
-use std::hash::Hash;
-use std::fmt::Debug;
-use std::{thread, time};
-use utils::collections::{merge_map, merge_set};
 use bit_set::BitSet;
 
-struct Environment<'time> {
-    map: &'time mut HashMap<String, BitSet>
-}
-
 #[derive(Debug)]
 pub enum MuErrors {
     VarNotFound(String)
 }
 
-#[derive(Clone)]
-enum Bound { None, Mu(MuFormula), Nu(MuFormula) }
-
-impl PartialEq for Bound {
-    fn eq(&self, other: &Bound) -> bool {
-        match self {
-            &Bound::Nu(_) => match *other { Bound::Nu(_) => true, _ => false },
-            &Bound::Mu(_) => match *other { Bound::Mu(_) => true, _ => false },
-            &Bound::None => match *other { 
-                Bound::None => true, _ => false
-             }
-        }
-    }
+// What the evaluator needs to know about a fixpoint subformula.
+struct Binder {
+    greatest: bool,
+    // sign of the nearest enclosing fixpoint, if any
+    surrounding: Option<bool>,
+    // open fixpoint subformulas with the same sign, this one included when open
+    resets: Vec<usize>
 }
-impl Eq for Bound {}
 
+// Approximations and binder information, all keyed by formula position so
+// variables that are rebound in different scopes do not interfere.
+struct Environment {
+    approximations: HashMap<usize, BitSet>,
+    binders: HashMap<usize, Binder>,
+    // position of each variable occurrence to the position of its binder
+    bindings: HashMap<usize, usize>,
+    weak: HashMap<(u32, String), BitSet>
+}
 
-fn find_variables(mu: &MuFormula, bound: Bound) -> HashMap<&MuFormula, Bound> {
-    let mut hm = HashMap::new();
-    hm.insert(mu, bound.clone());
+// Walks the formula, recording binders and bindings. Returns the positions of
+// the binders that occur free in `mu` and the fixpoints below it as
+// (position, sign, open) triples.
+fn analyse(mu: &MuFormula, scope: &mut Vec<(String, usize)>, surrounding: Option<bool>, e: &mut Environment) -> (HashSet<usize>, Vec<(usize, bool, bool)>) {
     match *mu {
-        // least fixpoint operator
-        MuFormula::Mu(_, _, ref f) => {
-            hm = merge_map(&find_variables(&f, Bound::Mu(mu.clone())), &hm);
+        MuFormula::Action(_, _) | MuFormula::Bool(_, _) => {
+            return (HashSet::new(), vec!());
         }
-        // greatest fixpoint operator
-        MuFormula::Nu(_, _, ref f) => {
-            hm = merge_map(&find_variables(&f, Bound::Nu(mu.clone())), &hm);
+        MuFormula::RecursionValue(p, ref c) => {
+            let mut free = HashSet::new();
+            if let Some(&(_, binder)) = scope.iter().rev().find(|&&(ref v, _)| v == c) {
+                e.bindings.insert(p, binder);
+                free.insert(binder);
+            }
+            return (free, vec!());
         }
-        MuFormula::Not(_, ref f) | MuFormula::DiamondOp (_,  _, ref f) | MuFormula::BoxOp (_,  _, ref f)  => {
-            hm = merge_map(&find_variables(&f, bound), &hm);
+        MuFormula::Not(_, ref f) | MuFormula::DiamondOp (_,  _, ref f) | MuFormula::BoxOp (_,  _, ref f) |
+        MuFormula::WeakDiamondOp (_,  _, ref f) | MuFormula::WeakBoxOp (_,  _, ref f) => {
+            return analyse(f, scope, surrounding, e);
         }
         MuFormula::And(_, ref f, ref g) | MuFormula::Or(_, ref f, ref g) => {
-            hm = merge_map(&merge_map(&find_variables(&f, bound.clone()), &find_variables(&g, bound)), &hm);
+            let (mut free, mut fixpoints) = analyse(f, scope, surrounding, e);
+            let (free_g, fixpoints_g) = analyse(g, scope, surrounding, e);
+            free.extend(free_g);
+            fixpoints.extend(fixpoints_g);
+            return (free, fixpoints);
         }
-        _ => {}
-    }
-    return hm;
-}
-
-fn free_variables(mu: &MuFormula, vars: &HashMap<&MuFormula, Bound>) -> HashSet<String> {
-    let mut hs = HashSet::new();
-    for var in find_children(mu) {
-        match var.clone() {
-            MuFormula::RecursionValue(_, ref v) => {
-                match vars.get(&var).unwrap_or(&Bound::None) {
-                    &Bound::Nu(ref var_mu) | &Bound::Mu(ref var_mu) => {
-                        if var_mu != mu {
-                            hs.insert(v.clone());
-                        }
-                    }
-                    _ => {}
-                }
-            }
-            _ => {}
+        MuFormula::Mu(p, ref c, ref f) | MuFormula::Nu(p, ref c, ref f) => {
+            let greatest = match *mu { MuFormula::Nu(_, _, _) => true, _ => false };
+            scope.push((c.clone(), p));
+            let (mut free, mut fixpoints) = analyse(f, scope, Some(greatest), e);
+            scope.pop();
+            free.remove(&p);
+            let open = !free.is_empty();
+            fixpoints.push((p, greatest, open));
+            let resets = fixpoints.iter()
+                .filter(|&&(_, sign, open)| sign == greatest && open)
+                .map(|&(q, _, _)| q)
+                .collect();
+            e.binders.insert(p, Binder { greatest: greatest, surrounding: surrounding, resets: resets });
+            return (free, fixpoints);
         }
     }
-    return hs;
 }
 
-pub fn evaluate<'time, L: Clone+Copy>(k: &'time MixedKripkeStructure<L>, mu: MuFormula) -> Result<BitSet, MuErrors> {
+pub fn evaluate<'time, L: Clone+Copy>(k: &'time MixedKripkeStructure<L>, mu: MuFormula, internal: &[String]) -> Result<BitSet, MuErrors> {
     let mut env = Environment {
-        map: &mut HashMap::new()
-     };
-    let variables = find_variables(&mu, Bound::None);
+        approximations: HashMap::new(),
+        binders: HashMap::new(),
+        bindings: HashMap::new(),
+        weak: weak_steps(k, &mu, internal)
+    };
+    analyse(&mu, &mut vec!(), None, &mut env);
 
-    for (key, bound) in variables.clone() {
-        match *key {
-            MuFormula::RecursionValue(_, ref c) => { 
-                match bound {
-                    Bound::None => {}
-                    Bound::Mu(mu) => {
-                        match mu {
-                            MuFormula::Mu(_, ref c2, _) => {
-                                if c == c2 {
-                                    env.map.insert(c.clone(), BitSet::new());
-                                }
-                            }
-                            _ => {}
-                        }
-      
-                    }
-                    Bound::Nu(mu) => {
-                        match mu {
-                            MuFormula::Nu(_, ref c2, _) => {
-                                if c == c2 {
-                                    env.map.insert(c.clone(), k.states.clone());
-                                }
-                            }
-                            _ => {}
-                        }
-                    }
-                }
-            }
-            _ => {}
-        }
-    }
+    // least fixpoints start at the empty set, greatest fixpoints at all states
+    let initial = env.binders.iter().map(|(&p, binder)| {
+        (p, if binder.greatest { k.states.clone() } else { BitSet::new() })
+    }).collect();
+    env.approximations = initial;
 
-    return eval(&variables, k, &mu, &mut env);
+    return eval(k, &mu, &mut env);
 }
 
-
+// Emerson-Lei: a fixpoint is iterated from its previous approximation, which
+// stays sound as long as the fixpoint of the opposite sign around it moves
+// monotonically. Only when entering a fixpoint whose surrounding binder has
+// the opposite sign, its open subformulas of the same sign are reset, since
+// the variables they depend on have moved in the wrong direction.
+// Negation is assumed to only be applied to closed subformulas.
 fn eval<'time, L: Clone+Copy>(
-    vars: &HashMap<&MuFormula, Bound>,
-    k: &'time MixedKripkeStructure<L>, 
-    mu: &MuFormula, 
-    e: &'time mut Environment
+    k: &'time MixedKripkeStructure<L>,
+    mu: &MuFormula,
+    e: &mut Environment
     ) -> Result<BitSet, MuErrors> {
-    
+
     return match *mu {
         // logic
-        MuFormula::Bool(_, ref b) => { 
+        MuFormula::Bool(_, ref b) => {
             let hs = BitSet::new();
             if *b {
                 return Ok(k.states.clone());
@@ -136,128 +114,217 @@ fn eval<'time, L: Clone+Copy>(
             return Ok(hs);
         },
         MuFormula::Not(_, ref f) => {
-            let result = try!(eval(vars, &k, f, e));
+            let result = try!(eval(&k, f, e));
             return Ok(k.states.difference(&result).collect::<BitSet>());
         },
         MuFormula::And(_, ref f, ref g) => {
-            let left = try!(eval(vars,&k, f, e));
-            let right = try!(eval(vars,&k, g, e));
+            let left = try!(eval(&k, f, e));
+            let right = try!(eval(&k, g, e));
             return Ok(left.intersection(&right).collect::<BitSet>());
         },
         MuFormula::Or(_, ref f, ref g) => {
-            let left = try!(eval(vars,&k, f, e));
-            let right = try!(eval(vars,&k, g, e));
+            let left = try!(eval(&k, f, e));
+            let right = try!(eval(&k, g, e));
             return Ok(left.union(&right).collect::<BitSet>());
         },
 
         // CTL
-        MuFormula::Action(_, _) => { 
+        MuFormula::Action(_, _) => {
             //TODO: implement label function
             Ok(BitSet::new())
         },
-        MuFormula::DiamondOp (p, ref ac, ref f) => { 
-            eval(vars, &k, 
-                &MuFormula::Not(p, box MuFormula::BoxOp(p, ac.clone(), box MuFormula::Not(p, box *f.clone()))),
-            e)
+        MuFormula::DiamondOp (_, ref ac, ref f) => {
+            let states = try!(eval(&k, f, e));
+            let mut result = BitSet::new();
+            for s in k.states.into_iter() {
+                if let Some(targets) = k.relations.get(&(s as u32, ac.clone())) {
+                    if !targets.is_disjoint(&states) {
+                        result.insert(s);
+                    }
+                }
+            }
+            return Ok(result);
         },
-        MuFormula::BoxOp (_, ref ac, ref f) => { 
-            let states = try!(eval(vars, &k, f, e));
+        MuFormula::BoxOp (_, ref ac, ref f) => {
+            let states = try!(eval(&k, f, e));
             let mut result = BitSet::new();
             for s in k.states.into_iter() {
-                let mut insert = true;
-                for pat in k.relations.get(&(s as u32, String::from(ac.clone()))).unwrap_or(&BitSet::new()) {
-                    if !(states.contains(*&pat as usize)) {
-                        insert = false;
+                let insert = match k.relations.get(&(s as u32, ac.clone())) {
+                    Some(targets) => targets.is_subset(&states),
+                    None => true
+                };
+                if insert {
+                    result.insert(s);
+                }
+            }
+            return Ok(result);
+        },
+        MuFormula::WeakDiamondOp (_, ref ac, ref f) => {
+            let states = try!(eval(&k, f, e));
+            let mut result = BitSet::new();
+            for s in k.states.into_iter() {
+                if let Some(targets) = e.weak.get(&(s as u32, ac.clone())) {
+                    if !targets.is_disjoint(&states) {
+                        result.insert(s);
                     }
                 }
+            }
+            return Ok(result);
+        },
+        MuFormula::WeakBoxOp (_, ref ac, ref f) => {
+            let states = try!(eval(&k, f, e));
+            let mut result = BitSet::new();
+            for s in k.states.into_iter() {
+                let insert = match e.weak.get(&(s as u32, ac.clone())) {
+                    Some(targets) => targets.is_subset(&states),
+                    None => true
+                };
                 if insert {
-                    result.insert(s as usize);
+                    result.insert(s);
                 }
             }
             return Ok(result);
         },
 
         // mu calculus
-        MuFormula::RecursionValue(_, ref c) => { 
-            let r = e.map.get(c).map(|r| (*r).clone()).ok_or(MuErrors::VarNotFound(c.clone()));
-            // println!("rec: {:?}", r);
-            return r;
+        MuFormula::RecursionValue(p, ref c) => {
+            let binder = try!(e.bindings.get(&p).cloned().ok_or(MuErrors::VarNotFound(c.clone())));
+            return e.approximations.get(&binder).cloned().ok_or(MuErrors::VarNotFound(c.clone()));
         },
 
-        // least fixpoint operator
-        MuFormula::Mu(_, ref c, ref f) => {
-            // search if bound is GFP
-            match vars.get(mu).unwrap_or(&Bound::None) {
-                &Bound::Nu(_) => {
-                    // find child mu's
-                    for child in find_children(mu) {
-                        match child.clone() {
-                            MuFormula::Mu(_, c2, _) => {
-                                let has_free_variables = free_variables(&child, vars).len() > 0;
-                                if has_free_variables {
-                                    e.map.insert(c2, BitSet::new());
-                                }
-                            }
-                            _ => {}
-                        }
-                    }
-                }
-                _ => {}
-            }
-
-            if !e.map.contains_key(&c.clone()) {
-                e.map.insert(c.clone(), BitSet::new());
+        MuFormula::Mu(p, _, ref f) | MuFormula::Nu(p, _, ref f) => {
+            let (greatest, resets) = {
+                let binder = &e.binders[&p];
+                let opposite = binder.surrounding.map_or(false, |sign| sign != binder.greatest);
+                (binder.greatest, if opposite { binder.resets.clone() } else { vec!() })
+            };
+            for q in resets {
+                let initial = if greatest { k.states.clone() } else { BitSet::new() };
+                e.approximations.insert(q, initial);
             }
 
-            let mut states = BitSet::new();
-            let mut nstates = BitSet::new();
             loop {
-                states = try!(e.map.get(&(c.clone())).map(|r| (*r).clone()).ok_or(MuErrors::VarNotFound(c.clone())));
-                nstates = try!(eval(&vars,&k, f, e));
-                e.map.insert((c.clone()), nstates.clone());
-                if states == nstates { 
-                    break; 
+                let states = e.approximations[&p].clone();
+                let nstates = try!(eval(&k, f, e));
+                e.approximations.insert(p, nstates.clone());
+                if states == nstates {
+                    return Ok(states);
                 }
             }
-            return Ok(states);
         },
+    };
+}
 
-        // greatest fixpMut operator
-        MuFormula::Nu(_, ref c, ref f) => {
-            // search if bound is LFP
-            match vars.clone().get(&mu).unwrap_or(&Bound::None) {
-                &Bound::Mu(_) => {
-                    // find child mu's
-                    for child in find_children(mu) {
-                        match child.clone() {
-                            MuFormula::Nu(_, c2, _) => {
-                                let has_free_variables = free_variables(&child, vars).len() > 0;
-                                if has_free_variables {
-                                    e.map.insert(c2, k.states.clone());
-                                }
-                            }
-                            _ => {}
-                        }
-                    }
+#[cfg(test)]
+mod tests {
+    use algorithms::{naive, emerson_lei, parity_game, zielonka, spm, local};
+    use algorithms::parity_game::{ParityGame, Solution};
+    use algorithms::spm::LiftingStrategy;
+    use parsers::mucalculus::read_mu_formula;
+    use utils::random::{Random, random_formula, random_kripke, lts};
+    use bit_set::BitSet;
+
+    // A strategy moves along an edge of the game into the region its player wins.
+    fn assert_strategy(game: &ParityGame, solution: &Solution, solver: &str) {
+        for v in 0..game.len() {
+            match solution.strategy[v] {
+                Some(w) => {
+                    assert_eq!(game.owner[v], solution.winner[v], "{} plays from a losing vertex", solver);
+                    assert!(game.successors[v].contains(&w), "{} moves along a missing edge", solver);
+                    assert_eq!(solution.winner[w], solution.winner[v], "{} leaves the winning region", solver);
                 }
-                _ => {}
+                None => assert!(game.owner[v] != solution.winner[v] || game.successors[v].is_empty(), "{} has no move", solver)
             }
+        }
+    }
 
-            if !e.map.contains_key(&c.clone()) {
-                e.map.insert(c.clone(), k.states.clone());
-            }
-            let mut states = BitSet::new();
-            let mut nstates = BitSet::new();
-            loop {
-                states = try!(e.map.get(&(c.clone())).map(|r| (*r).clone()).ok_or(MuErrors::VarNotFound(c.clone())));
-                nstates = try!(eval(&vars,&k, f, e));
-                e.map.insert((c.clone()), nstates.clone());
-                if states == nstates { 
-                    break; 
+    // Differential test of the Emerson-Lei evaluator, the parity game solvers
+    // and local checking against the naive evaluator.
+    #[test]
+    fn agrees_with_naive() {
+        let actions = vec!(String::from("a"), String::from("b"), String::from("tau"));
+        let internal = vec!(String::from("tau"));
+        for seed in 1..11 {
+            let mut rng = Random::new(seed);
+            for _ in 0..50 {
+                let states = 1 + rng.below(8);
+                let transitions = rng.below(3 * states);
+                let kripke = random_kripke(&mut rng, states, transitions, &actions);
+                let mu = random_formula(&mut rng, 6, &actions, true);
+                let expected = naive::evaluate(&kripke, mu.clone(), &internal).unwrap();
+                let game = parity_game::build(&kripke, &mu, &internal).unwrap();
+                assert_eq!(emerson_lei::evaluate(&kripke, mu.clone(), &internal).unwrap(), expected, "Emerson-Lei on {}", mu.to_string());
+                let mut solutions = vec!(
+                    ("Zielonka", zielonka::solve(&game)),
+                    ("SPM lifting predecessors", spm::solve(&game, LiftingStrategy::Predecessor))
+                );
+                // linear lifting sweeps the whole game after every lift, which is
+                // too slow for the larger games in a debug build
+                if game.len() <= 100 {
+                    solutions.push(("SPM lifting linearly", spm::solve(&game, LiftingStrategy::Linear)));
+                }
+                for (solver, solution) in solutions {
+                    assert_eq!(game.satisfying_states(&solution), expected, "{} on {}", solver, mu.to_string());
+                    assert_strategy(&game, &solution, solver);
                 }
+                assert_eq!(local::check(&kripke, &mu, &internal).unwrap().holds, expected.contains(0), "local on {}", mu.to_string());
             }
-     
-            return Ok(states);
-        },
-    };
-}
\ No newline at end of file
+        }
+    }
+
+    // Local checking on games too large for its first round, against the
+    // global verdict.
+    #[test]
+    fn local_agrees_on_large_models() {
+        let actions = vec!(String::from("a"), String::from("b"), String::from("tau"));
+        let internal = vec!(String::from("tau"));
+        for seed in 1..21 {
+            let mut rng = Random::new(seed);
+            let states = 100 + rng.below(100);
+            let transitions = 2 * states;
+            let kripke = random_kripke(&mut rng, states, transitions, &actions);
+            let mu = random_formula(&mut rng, 5, &actions, true);
+            let expected = emerson_lei::evaluate(&kripke, mu.clone(), &internal).unwrap().contains(0);
+            assert_eq!(local::check(&kripke, &mu, &internal).unwrap().holds, expected, "local on {}", mu.to_string());
+        }
+        // a long b-chain with an a-step halfway, so that the verdict is
+        // found after the budget of 256 vertices has doubled more than once,
+        // but before the whole game is explored
+        let mut chain = (0..2000).map(|s| (s, "b", s + 1)).collect::<Vec<(u64, &str, u64)>>();
+        chain.push((400, "a", 400));
+        let kripke = lts(&chain);
+        for &(formula, holds) in &[("mu X.<a>true || <b>X", true), ("nu X.[a]false && [b]X", false)] {
+            let result = local::check(&kripke, &read_mu_formula(formula).unwrap(), &internal).unwrap();
+            assert_eq!(result.holds, holds, "{}", formula);
+            assert!(!result.complete && result.explored > 256 + 512, "{} explored {} vertices", formula, result.explored);
+        }
+    }
+
+    // 0 -tau-> 1 -a-> 2 -tau-> 3, evaluated by every engine
+    #[test]
+    fn weak_modalities() {
+        let kripke = lts(&[(0, "tau", 1), (1, "a", 2), (2, "tau", 3)]);
+        let internal = vec!(String::from("tau"));
+        let cases = vec!(
+            ("<<a>>true", vec!(0, 1)),
+            ("<a>true", vec!(1)),
+            ("<<a>>[tau]false", vec!(0, 1)),
+            ("[[a]]false", vec!(2, 3)),
+            // a weak tau step may take no steps at all
+            ("<<tau>>true", vec!(0, 1, 2, 3)),
+            ("[[tau]]<a>true", vec!(1)),
+            ("<<tau>><a>true", vec!(0, 1))
+        );
+        for (formula, states) in cases {
+            let mu = read_mu_formula(formula).unwrap();
+            let expected = states.into_iter().collect::<BitSet>();
+            let game = parity_game::build(&kripke, &mu, &internal).unwrap();
+            assert_eq!(naive::evaluate(&kripke, mu.clone(), &internal).unwrap(), expected, "naive on {}", formula);
+            assert_eq!(emerson_lei::evaluate(&kripke, mu.clone(), &internal).unwrap(), expected, "Emerson-Lei on {}", formula);
+            assert_eq!(game.satisfying_states(&zielonka::solve(&game)), expected, "Zielonka on {}", formula);
+        }
+        // without internal actions a weak step is just a step
+        let mu = read_mu_formula("<<a>>true").unwrap();
+        assert_eq!(naive::evaluate(&kripke, mu, &[]).unwrap(), vec!(1).into_iter().collect::<BitSet>());
+    }
+}
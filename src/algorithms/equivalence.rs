@@ -0,0 +1,195 @@
+use parsers::kripke_structure::MixedKripkeStructure;
+use parsers::mucalculus::MuFormula;
+use algorithms::bisimulation::{Transitions, Partition, strong_bisimulation};
+use algorithms::branching::branching_bisimulation;
+use algorithms::closure::{tau_closure, weak_successors, saturate};
+use algorithms::distinguish::{refinement_history, distinguishing_formula};
+use std::collections::{HashMap, HashSet, VecDeque};
+use bit_set::BitSet;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Equivalence {
+    Strong,
+    Branching,
+    DivergenceBranching,
+    Weak,
+    Trace
+}
+
+pub struct Comparison {
+    pub equivalent: bool,
+    // a trace of one of the two that the other cannot do
+    pub trace: Option<Vec<String>>,
+    // for strong bisimulation, a formula that holds in the first and not in
+    // the second
+    pub formula: Option<MuFormula>
+}
+
+// The union of two LTSs, with the states of `b` moved past those of `a`.
+// Returns the union and the initial states of `a` and `b` in it.
+pub fn disjoint_union<L: Clone+Copy>(a: &MixedKripkeStructure<L>, b: &MixedKripkeStructure<L>) -> (MixedKripkeStructure<L>, usize, usize) {
+    let offset = a.states.iter().max().map_or(0, |s| s + 1);
+    let mut union = a.clone();
+    for s in b.states.iter() {
+        union.states.insert(s + offset);
+    }
+    for (&(s, ref action), targets) in b.relations.iter() {
+        union.relations.insert((s as u32 + offset as u32, action.clone()), targets.iter().map(|t| t + offset).collect());
+    }
+    let init_a = a.init_states.iter().next().unwrap_or(0);
+    let init_b = b.init_states.iter().next().unwrap_or(0) + offset;
+    union.init_states.insert(init_b);
+    (union, init_a, init_b)
+}
+
+fn same_block(t: &Transitions, p: &Partition, s: usize, u: usize) -> bool {
+    p.block[t.index[&s]] == p.block[t.index[&u]]
+}
+
+// A shortest trace that one of the LTSs can perform and the other cannot.
+// Internal actions are not observed. The two are explored together through
+// the subset construction, so trace equivalence is decided as a by-product.
+pub fn distinguishing_trace<L: Clone+Copy>(a: &MixedKripkeStructure<L>, b: &MixedKripkeStructure<L>, internal: &[String]) -> Option<Vec<String>> {
+    let closure_a = tau_closure(a, internal);
+    let closure_b = tau_closure(b, internal);
+    let mut visible = a.actions();
+    visible.extend(b.actions());
+    visible.sort();
+    visible.dedup();
+    visible.retain(|action| !internal.contains(action));
+
+    let start = (weak_successors(a, &closure_a, &a.init_states, None), weak_successors(b, &closure_b, &b.init_states, None));
+    let mut parent: HashMap<(BitSet, BitSet), ((BitSet, BitSet), String)> = HashMap::new();
+    let mut visited = HashSet::new();
+    visited.insert(start.clone());
+    let mut queue = VecDeque::new();
+    queue.push_back(start);
+    while let Some(pair) = queue.pop_front() {
+        for action in &visible {
+            let next = (weak_successors(a, &closure_a, &pair.0, Some(action)), weak_successors(b, &closure_b, &pair.1, Some(action)));
+            if next.0.is_empty() && next.1.is_empty() {
+                continue;
+            }
+            if next.0.is_empty() || next.1.is_empty() {
+                let mut trace = vec![action.clone()];
+                let mut current = pair.clone();
+                while let Some(&(ref previous, ref label)) = parent.get(&current) {
+                    trace.push(label.clone());
+                    current = previous.clone();
+                }
+                trace.reverse();
+                return Some(trace);
+            }
+            if visited.insert(next.clone()) {
+                parent.insert(next.clone(), (pair.clone(), action.clone()));
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+// Decides whether the initial states of `a` and `b` are equivalent; when they
+// are not, a distinguishing trace is given if there is one, and for strong
+// bisimulation a distinguishing formula.
+pub fn compare<L: Clone+Copy>(a: &MixedKripkeStructure<L>, b: &MixedKripkeStructure<L>, equivalence: Equivalence, internal: &[String]) -> Comparison {
+    let hidden: &[String] = if equivalence == Equivalence::Strong { &[] } else { internal };
+    let trace = distinguishing_trace(a, b, hidden);
+    if equivalence == Equivalence::Trace {
+        return Comparison { equivalent: trace.is_none(), trace: trace, formula: None };
+    }
+    let (union, s, u) = disjoint_union(a, b);
+    if equivalence == Equivalence::Strong {
+        let t = Transitions::new(&union);
+        let history = refinement_history(&t);
+        let formula = distinguishing_formula(&t, &history, s, u);
+        return Comparison { equivalent: formula.is_none(), trace: if formula.is_none() { None } else { trace }, formula: formula };
+    }
+    let equivalent = match equivalence {
+        Equivalence::Branching | Equivalence::DivergenceBranching => {
+            let t = Transitions::new(&union);
+            let p = branching_bisimulation(&t, internal, equivalence == Equivalence::DivergenceBranching);
+            same_block(&t, &p, s, u)
+        }
+        _ => {
+            let saturated = saturate(&union, internal);
+            let t = Transitions::new(&saturated);
+            same_block(&t, &strong_bisimulation(&t), s, u)
+        }
+    };
+    Comparison { equivalent: equivalent, trace: if equivalent { None } else { trace }, formula: None }
+}
+
+#[cfg(test)]
+mod tests {
+    use algorithms::equivalence::{compare, distinguishing_trace, Equivalence};
+    use algorithms::bisimulation::reduce_strong;
+    use algorithms::branching::reduce_branching;
+    use algorithms::emerson_lei;
+    use utils::random::{Random, random_kripke, lts};
+
+    #[test]
+    fn known_answers() {
+        let tau = vec!(String::from("tau"));
+        let early = lts(&[(0, "a", 1), (1, "b", 2), (0, "a", 3), (3, "c", 4)]);
+        let late = lts(&[(0, "a", 1), (1, "b", 2), (1, "c", 3)]);
+        let strong = compare(&early, &late, Equivalence::Strong, &tau);
+        assert!(!strong.equivalent);
+        // same traces, so only the formula tells them apart
+        assert!(strong.trace.is_none());
+        assert!(strong.formula.is_some());
+        assert!(compare(&early, &late, Equivalence::Trace, &tau).equivalent);
+        assert!(!compare(&early, &late, Equivalence::Weak, &tau).equivalent);
+
+        // a.tau.b and a.b
+        let inert = lts(&[(0, "a", 1), (1, "tau", 2), (2, "b", 3)]);
+        let ab = lts(&[(0, "a", 1), (1, "b", 2)]);
+        assert!(!compare(&inert, &ab, Equivalence::Strong, &tau).equivalent);
+        assert!(compare(&inert, &ab, Equivalence::Branching, &tau).equivalent);
+        assert!(compare(&inert, &ab, Equivalence::DivergenceBranching, &tau).equivalent);
+        assert!(compare(&inert, &ab, Equivalence::Weak, &tau).equivalent);
+
+        // an internal loop is only seen by divergence-preserving branching bisimulation
+        let divergent = lts(&[(0, "tau", 0), (0, "a", 1)]);
+        let a = lts(&[(0, "a", 1)]);
+        assert!(compare(&divergent, &a, Equivalence::Branching, &tau).equivalent);
+        assert!(!compare(&divergent, &a, Equivalence::DivergenceBranching, &tau).equivalent);
+    }
+
+    #[test]
+    fn shortest_distinguishing_traces() {
+        let tau = vec!(String::from("tau"));
+        let ab = lts(&[(0, "a", 1), (1, "b", 2)]);
+        let ac = lts(&[(0, "tau", 1), (1, "a", 2), (2, "c", 3)]);
+        assert_eq!(distinguishing_trace(&ab, &ac, &tau), Some(vec!(String::from("a"), String::from("b"))));
+        assert_eq!(distinguishing_trace(&ab, &ab, &tau), None);
+        // without hiding, the internal step is a trace of its own
+        assert_eq!(distinguishing_trace(&ab, &ac, &[]), Some(vec!(String::from("a"))));
+        let comparison = compare(&ab, &ac, Equivalence::Trace, &tau);
+        assert!(!comparison.equivalent && comparison.trace.is_some());
+    }
+
+    // Reductions are equivalent to what they reduce, and the formula found for
+    // inequivalent models holds in the first and fails in the second.
+    #[test]
+    fn reductions_are_equivalent() {
+        let tau = vec!(String::from("tau"));
+        let actions = vec!(String::from("a"), String::from("b"), String::from("tau"));
+        for seed in 1..51 {
+            let mut rng = Random::new(seed);
+            let states = 1 + rng.below(8);
+            let transitions = rng.below(3 * states);
+            let k = random_kripke(&mut rng, states, transitions, &actions);
+            assert!(compare(&k, &reduce_strong(&k), Equivalence::Strong, &tau).equivalent, "seed {}", seed);
+            assert!(compare(&k, &reduce_branching(&k, &tau, false), Equivalence::Branching, &tau).equivalent, "seed {}", seed);
+            assert!(compare(&k, &reduce_branching(&k, &tau, true), Equivalence::DivergenceBranching, &tau).equivalent, "seed {}", seed);
+            let transitions = rng.below(3 * states);
+            let other = random_kripke(&mut rng, states, transitions, &actions);
+            let comparison = compare(&k, &other, Equivalence::Strong, &tau);
+            if let Some(formula) = comparison.formula {
+                assert!(emerson_lei::evaluate(&k, formula.clone(), &[]).unwrap().contains(0), "seed {}", seed);
+                assert!(!emerson_lei::evaluate(&other, formula, &[]).unwrap().contains(0), "seed {}", seed);
+            }
+        }
+    }
+}
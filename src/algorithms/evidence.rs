@@ -0,0 +1,133 @@
+use parsers::mucalculus::{MuFormula, subformula};
+use parsers::kripke_structure::MixedKripkeStructure;
+use parsers::aldebaran::{AutFile, AutHeader, AutEdge};
+use algorithms::parity_game::{GameBuilder, GameErrors, ParityGame, Player, Node, Solution};
+use algorithms::zielonka;
+use algorithms::closure::weak_path;
+use std::collections::{HashMap, HashSet};
+use bit_set::BitSet;
+
+// Why the formula holds or fails in the initial state: the part of the LTS
+// the winner of the parity game needs to win, and the choices the winner
+// makes there.
+pub struct Evidence {
+    pub holds: bool,
+    pub initial: u32,
+    pub transitions: Vec<(u32, String, u32)>,
+    // moves of the winner at vertices where there was a choice, as
+    // (state, subformula, next state, next subformula)
+    pub choices: Vec<(u32, String, u32, String)>
+}
+
+impl Evidence {
+    // The evidence as a sequence of transitions from the initial state, when
+    // it is a single path, possibly ending in a loop.
+    pub fn trace(&self) -> Option<Vec<(u32, String, u32)>> {
+        let mut outgoing: HashMap<u32, (String, u32)> = HashMap::new();
+        for &(s, ref a, t) in &self.transitions {
+            if outgoing.insert(s, (a.clone(), t)).is_some() {
+                return None;
+            }
+        }
+        let mut trace = vec!();
+        let mut visited = HashSet::new();
+        let mut s = self.initial;
+        while visited.insert(s) {
+            match outgoing.get(&s) {
+                Some(&(ref a, t)) => {
+                    trace.push((s, a.clone(), t));
+                    s = t;
+                }
+                None => break
+            }
+        }
+        if trace.len() == self.transitions.len() { Some(trace) } else { None }
+    }
+
+    // The evidence as an LTS on its own; the initial state becomes state 0
+    // and the other states are numbered in order of appearance.
+    pub fn to_aut(&self) -> AutFile {
+        let mut ids: HashMap<u32, u64> = HashMap::new();
+        ids.insert(self.initial, 0);
+        let mut edges = vec!();
+        for &(s, ref a, t) in &self.transitions {
+            let next = ids.len() as u64;
+            let start = *ids.entry(s).or_insert(next);
+            let next = ids.len() as u64;
+            let end = *ids.entry(t).or_insert(next);
+            edges.push(AutEdge { start_state: start, label: a.clone(), end_state: end });
+        }
+        AutFile {
+            header: AutHeader { first_state: 0, nr_of_transitions: edges.len(), nr_of_states: ids.len() },
+            edges: edges
+        }
+    }
+}
+
+fn describe(game: &ParityGame, position: usize) -> String {
+    subformula(&game.formula, position).map(|f| f.to_string()).unwrap_or(String::new())
+}
+
+// Follows the strategy of the winner of `root` and every move of the
+// opponent, collecting the transitions of the LTS that are used by the
+// modalities along the way. A weak modality uses the transitions of a
+// shortest path that makes up its weak step.
+fn collect<L: Clone+Copy>(k: &MixedKripkeStructure<L>, internal: &[String], game: &ParityGame, solution: &Solution, root: usize, holds: bool, initial: u32) -> Evidence {
+    let winner = solution.winner[root];
+    let mut transitions = vec!();
+    let mut seen_transitions = HashSet::new();
+    let mut choices = vec!();
+    let mut visited = BitSet::new();
+    let mut stack = vec![root];
+    visited.insert(root);
+    while let Some(v) = stack.pop() {
+        let (s, position) = match game.labels[v] {
+            Some(label) => label,
+            None => continue
+        };
+        let moves = if game.owner[v] == winner {
+            solution.strategy[v].into_iter().collect::<Vec<usize>>()
+        } else {
+            game.successors[v].clone()
+        };
+        for &w in &moves {
+            if let Some((t, next)) = game.labels[w] {
+                match game.nodes[position] {
+                    Node::DiamondOp(ref a, _) | Node::BoxOp(ref a, _) => {
+                        if seen_transitions.insert((s, a.clone(), t)) {
+                            transitions.push((s, a.clone(), t));
+                        }
+                    }
+                    Node::WeakDiamondOp(ref a, _) | Node::WeakBoxOp(ref a, _) => {
+                        for step in weak_path(k, s, a, t, internal).unwrap_or(vec!()) {
+                            if seen_transitions.insert(step.clone()) {
+                                transitions.push(step);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+                if game.owner[v] == winner && game.successors[v].len() > 1 {
+                    choices.push((s, describe(game, position), t, describe(game, next)));
+                }
+            }
+            if visited.insert(w) {
+                stack.push(w);
+            }
+        }
+    }
+    Evidence { holds: holds, initial: initial, transitions: transitions, choices: choices }
+}
+
+// Explains the verdict for the first initial state by solving the parity
+// game explored from that state.
+pub fn evidence<L: Clone+Copy>(k: &MixedKripkeStructure<L>, mu: &MuFormula, internal: &[String]) -> Result<Evidence, GameErrors> {
+    let mut builder = try!(GameBuilder::new(k, mu, internal));
+    let initial = k.init_states.iter().next().unwrap_or(0) as u32;
+    let root = builder.seed(initial);
+    builder.explore(None);
+    let game = builder.game;
+    let solution = zielonka::solve(&game);
+    let holds = solution.winner[root] == Player::Even;
+    Ok(collect(k, internal, &game, &solution, root, holds, initial))
+}
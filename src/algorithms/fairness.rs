@@ -0,0 +1,295 @@
+use parsers::ctl::CtlFormula;
+use parsers::kripke_structure::MixedKripkeStructure;
+use algorithms::scc::decompose;
+use std::collections::HashMap;
+use bit_set::BitSet;
+
+// A fairness constraint over a set of action labels. A path is weakly fair
+// for the set if, whenever one of its actions is continuously enabled from
+// some point on, one of them is taken infinitely often. It is strongly fair
+// if an action that is enabled infinitely often is taken infinitely often.
+#[derive(Clone, Debug)]
+pub enum Fairness {
+    Weak(Vec<String>),
+    Strong(Vec<String>)
+}
+
+// states with an `actions`-transition into `target`
+fn pre_exists<L: Clone+Copy>(k: &MixedKripkeStructure<L>, target: &BitSet, actions: &[String]) -> BitSet {
+    let mut result = BitSet::new();
+    for (&(s, ref a), successors) in k.relations.iter() {
+        if actions.contains(a) && !successors.is_disjoint(target) {
+            result.insert(s as usize);
+        }
+    }
+    result
+}
+
+fn complement<L: Clone+Copy>(k: &MixedKripkeStructure<L>, states: &BitSet) -> BitSet {
+    k.states.difference(states).collect::<BitSet>()
+}
+
+// states in `within` that can reach `target` using `actions` while staying in `within`
+fn reach_within<L: Clone+Copy>(k: &MixedKripkeStructure<L>, within: &BitSet, target: &BitSet, actions: &[String]) -> BitSet {
+    let mut predecessors: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (&(s, ref a), targets) in k.relations.iter() {
+        if actions.contains(a) && within.contains(s as usize) {
+            for t in targets.iter() {
+                predecessors.entry(t).or_insert(vec!()).push(s as usize);
+            }
+        }
+    }
+    let mut result = target.intersection(within).collect::<BitSet>();
+    let mut stack = result.iter().collect::<Vec<usize>>();
+    while let Some(t) = stack.pop() {
+        if let Some(sources) = predecessors.get(&t) {
+            for &s in sources {
+                if result.insert(s) {
+                    stack.push(s);
+                }
+            }
+        }
+    }
+    result
+}
+
+// Whether some transition labelled with one of `set` is enabled in `s`.
+fn enables<L: Clone+Copy>(k: &MixedKripkeStructure<L>, s: u32, set: &[String]) -> bool {
+    set.iter().any(|a| k.relations.get(&(s, a.clone())).map_or(false, |t| !t.is_empty()))
+}
+
+// Whether a transition labelled with one of `set` stays inside `component`.
+fn taken_within<L: Clone+Copy>(k: &MixedKripkeStructure<L>, component: &BitSet, set: &[String], actions: &[String]) -> bool {
+    component.iter().any(|s| set.iter().any(|a| {
+        actions.contains(a) && k.relations.get(&(s as u32, a.clone())).map_or(false, |t| !t.is_disjoint(component))
+    }))
+}
+
+// Nontrivial strongly connected components of the graph on `within` using `actions`.
+fn components<L: Clone+Copy>(k: &MixedKripkeStructure<L>, within: &BitSet, actions: &[String]) -> Vec<BitSet> {
+    let decomposition = decompose(k, within, Some(actions));
+    let nontrivial = decomposition.nontrivial;
+    decomposition.components.into_iter().enumerate().filter(|&(c, _)| nontrivial[c]).map(|(_, component)| component).collect()
+}
+
+// Union of the components inside `within` that admit a fair cycle. Strongly
+// fair sets whose actions are enabled but never taken in a component are
+// handled by removing the enabling states and decomposing again.
+fn fair_components<L: Clone+Copy>(k: &MixedKripkeStructure<L>, within: &BitSet, actions: &[String], constraints: &[Fairness]) -> BitSet {
+    let mut result = BitSet::new();
+    for component in components(k, within, actions) {
+        let mut fair = true;
+        let mut remove = BitSet::new();
+        for constraint in constraints {
+            match *constraint {
+                Fairness::Weak(ref set) => {
+                    if !taken_within(k, &component, set, actions) && component.iter().all(|s| enables(k, s as u32, set)) {
+                        fair = false;
+                    }
+                }
+                Fairness::Strong(ref set) => {
+                    if !taken_within(k, &component, set, actions) {
+                        remove.extend(component.iter().filter(|&s| enables(k, s as u32, set)));
+                    }
+                }
+            }
+        }
+        if !fair {
+            continue;
+        }
+        if remove.is_empty() {
+            result.union_with(&component);
+        } else {
+            let rest = component.difference(&remove).collect::<BitSet>();
+            result.union_with(&fair_components(k, &rest, actions, constraints));
+        }
+    }
+    result
+}
+
+// E G phi restricted to fair paths over `actions`. A path that ends in a
+// state without `actions`-transitions is fair, as nothing is enabled there.
+pub fn fair_eg<L: Clone+Copy>(k: &MixedKripkeStructure<L>, phi: &BitSet, actions: &[String], constraints: &[Fairness]) -> BitSet {
+    let mut ends = fair_components(k, phi, actions, constraints);
+    ends.extend(phi.iter().filter(|&s| !enables(k, s as u32, actions)));
+    reach_within(k, phi, &ends, actions)
+}
+
+// Evaluates a CTL formula where the path quantifiers only range over fair
+// maximal paths: the fair infinite paths and the paths that end in a
+// deadlock. Without constraints this agrees with the translation to the
+// µ-calculus.
+pub fn evaluate<L: Clone+Copy>(k: &MixedKripkeStructure<L>, ctl: &CtlFormula, constraints: &[Fairness]) -> BitSet {
+    let alphabet = k.actions();
+    let mut restrictions = vec!();
+    collect_restrictions(ctl, &mut restrictions);
+    // the states where a fair path starts, for the actions of every path quantifier
+    let mut fair = HashMap::new();
+    for r in restrictions {
+        let actions = r.unwrap_or(alphabet.clone());
+        if !fair.contains_key(&actions) {
+            let states = fair_eg(k, &k.states, &actions, constraints);
+            fair.insert(actions, states);
+        }
+    }
+    eval(k, ctl, constraints, &alphabet, &fair)
+}
+
+fn collect_restrictions(ctl: &CtlFormula, result: &mut Vec<Option<Vec<String>>>) {
+    match *ctl {
+        CtlFormula::Bool(_) => {}
+        CtlFormula::Not(ref f) | CtlFormula::DiamondOp(_, ref f) | CtlFormula::BoxOp(_, ref f) => collect_restrictions(f, result),
+        CtlFormula::And(ref f, ref g) | CtlFormula::Or(ref f, ref g) => {
+            collect_restrictions(f, result);
+            collect_restrictions(g, result);
+        }
+        CtlFormula::EX(ref r, ref f) | CtlFormula::AX(ref r, ref f) | CtlFormula::EF(ref r, ref f) |
+        CtlFormula::AF(ref r, ref f) | CtlFormula::EG(ref r, ref f) | CtlFormula::AG(ref r, ref f) => {
+            result.push(r.clone());
+            collect_restrictions(f, result);
+        }
+        CtlFormula::EU(ref r, ref f, ref g) | CtlFormula::AU(ref r, ref f, ref g) => {
+            result.push(r.clone());
+            collect_restrictions(f, result);
+            collect_restrictions(g, result);
+        }
+    }
+}
+
+fn eval<L: Clone+Copy>(k: &MixedKripkeStructure<L>, ctl: &CtlFormula, constraints: &[Fairness], alphabet: &Vec<String>, fair: &HashMap<Vec<String>, BitSet>) -> BitSet {
+    let restrict = |r: &Option<Vec<String>>| r.clone().unwrap_or(alphabet.clone());
+    match *ctl {
+        CtlFormula::Bool(b) => if b { k.states.clone() } else { BitSet::new() },
+        CtlFormula::Not(ref f) => complement(k, &eval(k, f, constraints, alphabet, fair)),
+        CtlFormula::And(ref f, ref g) => {
+            eval(k, f, constraints, alphabet, fair).intersection(&eval(k, g, constraints, alphabet, fair)).collect::<BitSet>()
+        }
+        CtlFormula::Or(ref f, ref g) => {
+            eval(k, f, constraints, alphabet, fair).union(&eval(k, g, constraints, alphabet, fair)).collect::<BitSet>()
+        }
+        CtlFormula::DiamondOp(ref ac, ref f) => {
+            pre_exists(k, &eval(k, f, constraints, alphabet, fair), &[ac.clone()])
+        }
+        CtlFormula::BoxOp(ref ac, ref f) => {
+            let violating = complement(k, &eval(k, f, constraints, alphabet, fair));
+            complement(k, &pre_exists(k, &violating, &[ac.clone()]))
+        }
+        CtlFormula::EX(ref r, ref f) => {
+            let actions = restrict(r);
+            ex(k, &eval(k, f, constraints, alphabet, fair), &actions, &fair[&actions])
+        }
+        CtlFormula::AX(ref r, ref f) => {
+            let actions = restrict(r);
+            let violating = complement(k, &eval(k, f, constraints, alphabet, fair));
+            complement(k, &ex(k, &violating, &actions, &fair[&actions]))
+        }
+        CtlFormula::EF(ref r, ref f) => {
+            let actions = restrict(r);
+            eu(k, &k.states, &eval(k, f, constraints, alphabet, fair), &actions, &fair[&actions])
+        }
+        CtlFormula::AG(ref r, ref f) => {
+            let actions = restrict(r);
+            let violating = complement(k, &eval(k, f, constraints, alphabet, fair));
+            complement(k, &eu(k, &k.states, &violating, &actions, &fair[&actions]))
+        }
+        CtlFormula::EG(ref r, ref f) => {
+            let actions = restrict(r);
+            fair_eg(k, &eval(k, f, constraints, alphabet, fair), &actions, constraints)
+        }
+        CtlFormula::AF(ref r, ref f) => {
+            let actions = restrict(r);
+            let violating = complement(k, &eval(k, f, constraints, alphabet, fair));
+            complement(k, &fair_eg(k, &violating, &actions, constraints))
+        }
+        CtlFormula::EU(ref r, ref f, ref g) => {
+            let actions = restrict(r);
+            eu(k, &eval(k, f, constraints, alphabet, fair), &eval(k, g, constraints, alphabet, fair), &actions, &fair[&actions])
+        }
+        // A[f U g] = !(E[!g U (!f && !g)] || EG !g)
+        CtlFormula::AU(ref r, ref f, ref g) => {
+            let actions = restrict(r);
+            let not_f = complement(k, &eval(k, f, constraints, alphabet, fair));
+            let not_g = complement(k, &eval(k, g, constraints, alphabet, fair));
+            let both = not_f.intersection(&not_g).collect::<BitSet>();
+            let mut violating = eu(k, &not_g, &both, &actions, &fair[&actions]);
+            violating.union_with(&fair_eg(k, &not_g, &actions, constraints));
+            complement(k, &violating)
+        }
+    }
+}
+
+// fair EX f = EX (f && fair)
+fn ex<L: Clone+Copy>(k: &MixedKripkeStructure<L>, f: &BitSet, actions: &[String], fair: &BitSet) -> BitSet {
+    pre_exists(k, &f.intersection(fair).collect::<BitSet>(), actions)
+}
+
+// fair E[f U g] = E[f U (g && fair)]
+fn eu<L: Clone+Copy>(k: &MixedKripkeStructure<L>, f: &BitSet, g: &BitSet, actions: &[String], fair: &BitSet) -> BitSet {
+    let goal = g.intersection(fair).collect::<BitSet>();
+    let mut within = f.clone();
+    within.union_with(&goal);
+    reach_within(k, &within, &goal, actions)
+}
+
+#[cfg(test)]
+mod tests {
+    use algorithms::{fairness, naive};
+    use algorithms::fairness::Fairness;
+    use parsers::ctl::{read_ctl_formula, to_mu_formula};
+    use parsers::kripke_structure::MixedKripkeStructure;
+    use utils::random::{Random, random_kripke, lts};
+    use bit_set::BitSet;
+
+    fn check(k: &MixedKripkeStructure<()>, formula: &str, constraints: &[Fairness], expected: Vec<usize>) {
+        let ctl = read_ctl_formula(formula).unwrap();
+        assert_eq!(fairness::evaluate(k, &ctl, constraints), expected.into_iter().collect::<BitSet>(), "{} under {:?}", formula, constraints);
+    }
+
+    fn actions(names: &[&str]) -> Vec<String> {
+        names.iter().map(|a| a.to_string()).collect()
+    }
+
+    // A constraint on an action that never occurs rules out no path, so the
+    // verdicts must be those of the translation, deadlocks included.
+    #[test]
+    fn vacuous_constraint_keeps_verdicts() {
+        let actions = vec!(String::from("a"), String::from("b"), String::from("tau"));
+        let formulas = ["AG EX true", "AF <a>true", "EG <b>true", "A[<a>true U <b>true]", "EF{a} AX false", "AX{b} EG{a} true"];
+        let constraints = vec!(Fairness::Weak(vec!(String::from("x"))), Fairness::Strong(vec!(String::from("x"))));
+        let mut rng = Random::new(1);
+        for _ in 0..100 {
+            let states = 1 + rng.below(8);
+            let transitions = rng.below(2 * states);
+            let k = random_kripke(&mut rng, states, transitions, &actions);
+            for s in formulas.iter() {
+                let ctl = read_ctl_formula(&s.replace(" ", "")).unwrap();
+                let expected = naive::evaluate(&k, to_mu_formula(&ctl, &k.actions()), &[]).unwrap();
+                assert_eq!(fairness::evaluate(&k, &ctl, &constraints), expected, "{}", s);
+            }
+        }
+    }
+
+    // 0 -tau-> 0 and 0 -a-> 1: idling on tau forever ignores the a that is
+    // always enabled, so weak fairness forces the a-step.
+    #[test]
+    fn weak_fairness_excludes_ignored_actions() {
+        let k = lts(&[(0, "tau", 0), (0, "a", 1)]);
+        check(&k, "AF[tau]false", &[], vec!(1));
+        check(&k, "AF[tau]false", &[Fairness::Weak(actions(&["a"]))], vec!(0, 1));
+        check(&k, "EG<tau>true", &[], vec!(0));
+        check(&k, "EG<tau>true", &[Fairness::Weak(actions(&["a"]))], vec!());
+        check(&k, "EX{tau}true", &[Fairness::Weak(actions(&["a"]))], vec!());
+    }
+
+    // 0 -tau-> 1 -tau-> 0 and 0 -a-> 2: a is enabled infinitely often on the
+    // cycle but not continuously, so only strong fairness forces it.
+    #[test]
+    fn strong_fairness_excludes_intermittently_enabled_actions() {
+        let k = lts(&[(0, "tau", 1), (1, "tau", 0), (0, "a", 2)]);
+        check(&k, "AF[tau]false", &[], vec!(2));
+        check(&k, "AF[tau]false", &[Fairness::Weak(actions(&["a"]))], vec!(2));
+        check(&k, "AF[tau]false", &[Fairness::Strong(actions(&["a"]))], vec!(0, 1, 2));
+        check(&k, "EF{tau}<a>true", &[Fairness::Strong(actions(&["a"]))], vec!());
+        check(&k, "EF{tau}<a>true", &[Fairness::Weak(actions(&["a"]))], vec!(0, 1));
+    }
+}
@@ -0,0 +1,58 @@
+use parsers::mucalculus::MuFormula;
+use parsers::kripke_structure::MixedKripkeStructure;
+use algorithms::parity_game::{GameBuilder, GameErrors, ParityGame, Player, TRUE_VERTEX, FALSE_VERTEX};
+use algorithms::zielonka;
+
+// Vertices expanded before the partial game is first solved; the budget
+// doubles after every inconclusive round.
+const INITIAL_BUDGET: usize = 256;
+
+pub struct LocalResult {
+    pub holds: bool,
+    // number of vertices of the game that were expanded
+    pub explored: usize,
+    // whether the verdict needed the whole reachable game
+    pub complete: bool
+}
+
+// The partial game in which every vertex that is not expanded yet moves to
+// `sink`, i.e. is won by the player the sink belongs to.
+fn close<'a, L: Clone+Copy>(builder: &GameBuilder<'a, L>, sink: usize) -> ParityGame {
+    let mut game = builder.game.clone();
+    for v in 0..game.len() {
+        if !builder.is_expanded(v) {
+            game.successors[v].push(sink);
+            game.predecessors[sink].push(v);
+        }
+    }
+    game
+}
+
+// Decides whether the formula holds in all initial states by exploring the
+// parity game from those states only. After every round the partial game is
+// solved twice: once with the unexplored vertices lost by Even, which proves
+// the formula when the initial vertices are still won, and once with them
+// won by Even, which refutes it when an initial vertex is still lost.
+pub fn check<L: Clone+Copy>(k: &MixedKripkeStructure<L>, mu: &MuFormula, internal: &[String]) -> Result<LocalResult, GameErrors> {
+    let mut builder = try!(GameBuilder::new(k, mu, internal));
+    let roots = k.init_states.iter().map(|s| builder.seed(s as u32)).collect::<Vec<usize>>();
+    let mut budget = INITIAL_BUDGET;
+    loop {
+        let complete = builder.explore(Some(budget));
+        let explored = builder.explored();
+        if complete {
+            let solution = zielonka::solve(&builder.game);
+            let holds = roots.iter().all(|&v| solution.winner[v] == Player::Even);
+            return Ok(LocalResult { holds: holds, explored: explored, complete: true });
+        }
+        let pessimistic = zielonka::solve(&close(&builder, FALSE_VERTEX));
+        if roots.iter().all(|&v| pessimistic.winner[v] == Player::Even) {
+            return Ok(LocalResult { holds: true, explored: explored, complete: false });
+        }
+        let optimistic = zielonka::solve(&close(&builder, TRUE_VERTEX));
+        if roots.iter().any(|&v| optimistic.winner[v] == Player::Odd) {
+            return Ok(LocalResult { holds: false, explored: explored, complete: false });
+        }
+        budget *= 2;
+    }
+}
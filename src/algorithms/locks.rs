@@ -0,0 +1,106 @@
+use parsers::kripke_structure::MixedKripkeStructure;
+use algorithms::trace::shortest_path;
+use algorithms::scc::decompose;
+use std::collections::HashMap;
+use bit_set::BitSet;
+
+// Labels of the actions that are not observable.
+pub fn internal_actions() -> Vec<String> {
+    vec!(String::from("tau"), String::from("i"))
+}
+
+// States reachable from the initial states.
+pub fn reachable<L: Clone+Copy>(k: &MixedKripkeStructure<L>) -> BitSet {
+    let mut successors: HashMap<u32, Vec<usize>> = HashMap::new();
+    for (&(s, _), targets) in k.relations.iter() {
+        successors.entry(s).or_insert(vec!()).extend(targets.iter());
+    }
+    let mut result = k.init_states.clone();
+    let mut stack = result.iter().collect::<Vec<usize>>();
+    while let Some(s) = stack.pop() {
+        if let Some(targets) = successors.get(&(s as u32)) {
+            for &t in targets {
+                if result.insert(t) {
+                    stack.push(t);
+                }
+            }
+        }
+    }
+    result
+}
+
+// A reachable deadlock, with a shortest trace from the initial state.
+pub struct Deadlock {
+    pub states: BitSet,
+    pub trace: Vec<(u32, String, u32)>
+}
+
+pub fn deadlocks<L: Clone+Copy>(k: &MixedKripkeStructure<L>) -> Option<Deadlock> {
+    let mut states = reachable(k);
+    for (&(s, _), targets) in k.relations.iter() {
+        if !targets.is_empty() {
+            states.remove(s as usize);
+        }
+    }
+    if states.is_empty() {
+        return None;
+    }
+    let trace = shortest_path(k, &k.init_states, &states, &k.actions()).unwrap_or(vec!());
+    Some(Deadlock { states: states, trace: trace })
+}
+
+fn singleton(s: usize) -> BitSet {
+    let mut result = BitSet::new();
+    result.insert(s);
+    result
+}
+
+// A set of reachable states that can perform internal actions among each
+// other forever, with a shortest trace into it from one of the initial
+// states and a cycle of internal actions from the end of that trace.
+pub struct Livelock {
+    pub states: BitSet,
+    pub initial: u32,
+    pub trace: Vec<(u32, String, u32)>,
+    pub cycle: Vec<(u32, String, u32)>
+}
+
+// The livelocks are the nontrivial strongly connected components of the
+// reachable part of the graph restricted to `internal` actions.
+pub fn livelocks<L: Clone+Copy>(k: &MixedKripkeStructure<L>, internal: &[String]) -> Vec<Livelock> {
+    let decomposition = decompose(k, &reachable(k), Some(internal));
+    let actions = k.actions();
+    let mut result = vec!();
+    for (c, members) in decomposition.components.into_iter().enumerate() {
+        if !decomposition.nontrivial[c] {
+            continue;
+        }
+        // the search starts from all initial states, so an empty trace means
+        // that an initial state lies in the component
+        let trace = match shortest_path(k, &k.init_states, &members, &actions) {
+            Some(trace) => trace,
+            None => continue
+        };
+        let entry = match trace.last() {
+            Some(&(_, _, t)) => t as usize,
+            None => match k.init_states.iter().find(|&s| members.contains(s)) {
+                Some(s) => s,
+                None => continue
+            }
+        };
+        let initial = trace.first().map_or(entry as u32, |&(s, _, _)| s);
+        // leave the entry by an internal step inside the component and return
+        let mut cycle = vec!();
+        for a in internal {
+            let next = k.relations.get(&(entry as u32, a.clone())).and_then(|targets| targets.iter().find(|&t| members.contains(t)));
+            if let Some(t) = next {
+                let back = shortest_path(k, &singleton(t), &singleton(entry), internal).unwrap_or(vec!());
+                cycle.push((entry as u32, a.clone(), t as u32));
+                cycle.extend(back);
+                break;
+            }
+        }
+        result.push(Livelock { states: members, initial: initial, trace: trace, cycle: cycle });
+    }
+    result
+}
@@ -0,0 +1,479 @@
+use parsers::ltl::LtlFormula;
+use parsers::kripke_structure::MixedKripkeStructure;
+use utils::graph::strongly_connected_components;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use bit_set::BitSet;
+
+// LTL formula in negation normal form; G and F are expressed with R and U.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum Nnf {
+    True,
+    False,
+    Is(String),
+    IsNot(String),
+    And(Box<Nnf>, Box<Nnf>),
+    Or(Box<Nnf>, Box<Nnf>),
+    Next(Box<Nnf>),
+    Until(Box<Nnf>, Box<Nnf>),
+    Release(Box<Nnf>, Box<Nnf>),
+}
+
+fn nnf(f: &LtlFormula, negate: bool) -> Nnf {
+    match *f {
+        LtlFormula::Bool(b) => if b != negate { Nnf::True } else { Nnf::False },
+        LtlFormula::Action(ref a) => if negate { Nnf::IsNot(a.clone()) } else { Nnf::Is(a.clone()) },
+        LtlFormula::Not(ref g) => nnf(g, !negate),
+        LtlFormula::And(ref g, ref h) => {
+            if negate {
+                Nnf::Or(box nnf(g, true), box nnf(h, true))
+            } else {
+                Nnf::And(box nnf(g, false), box nnf(h, false))
+            }
+        }
+        LtlFormula::Or(ref g, ref h) => {
+            if negate {
+                Nnf::And(box nnf(g, true), box nnf(h, true))
+            } else {
+                Nnf::Or(box nnf(g, false), box nnf(h, false))
+            }
+        }
+        LtlFormula::Next(ref g) => Nnf::Next(box nnf(g, negate)),
+        // G g = false R g, !G g = true U !g
+        LtlFormula::Globally(ref g) => {
+            if negate {
+                Nnf::Until(box Nnf::True, box nnf(g, true))
+            } else {
+                Nnf::Release(box Nnf::False, box nnf(g, false))
+            }
+        }
+        // F g = true U g, !F g = false R !g
+        LtlFormula::Finally(ref g) => {
+            if negate {
+                Nnf::Release(box Nnf::False, box nnf(g, true))
+            } else {
+                Nnf::Until(box Nnf::True, box nnf(g, false))
+            }
+        }
+        LtlFormula::Until(ref g, ref h) => {
+            if negate {
+                Nnf::Release(box nnf(g, true), box nnf(h, true))
+            } else {
+                Nnf::Until(box nnf(g, false), box nnf(h, false))
+            }
+        }
+        LtlFormula::Release(ref g, ref h) => {
+            if negate {
+                Nnf::Until(box nnf(g, true), box nnf(h, true))
+            } else {
+                Nnf::Release(box nnf(g, false), box nnf(h, false))
+            }
+        }
+    }
+}
+
+fn collect_untils(f: &Nnf, untils: &mut Vec<Nnf>) {
+    match *f {
+        Nnf::And(ref g, ref h) | Nnf::Or(ref g, ref h) | Nnf::Release(ref g, ref h) => {
+            collect_untils(g, untils);
+            collect_untils(h, untils);
+        }
+        Nnf::Until(ref g, ref h) => {
+            if !untils.contains(f) {
+                untils.push(f.clone());
+            }
+            collect_untils(g, untils);
+            collect_untils(h, untils);
+        }
+        Nnf::Next(ref g) => collect_untils(g, untils),
+        _ => {}
+    }
+}
+
+// One way of satisfying the obligations of an automaton state: a constraint
+// on the action taken now, the obligations for the next position and the
+// untils whose right-hand side got postponed.
+#[derive(Clone)]
+struct Cover {
+    is: Option<String>,
+    is_not: BTreeSet<String>,
+    next: BTreeSet<Nnf>,
+    postponed: BTreeSet<Nnf>
+}
+
+fn expand(mut todo: Vec<Nnf>, mut cover: Cover, covers: &mut Vec<Cover>) {
+    let f = match todo.pop() {
+        Some(f) => f,
+        None => {
+            covers.push(cover);
+            return;
+        }
+    };
+    match f {
+        Nnf::True => expand(todo, cover, covers),
+        Nnf::False => {}
+        Nnf::Is(a) => {
+            // exactly one action is taken at every position
+            if cover.is.as_ref().map_or(false, |b| *b != a) || cover.is_not.contains(&a) {
+                return;
+            }
+            cover.is = Some(a);
+            expand(todo, cover, covers);
+        }
+        Nnf::IsNot(a) => {
+            if cover.is.as_ref() == Some(&a) {
+                return;
+            }
+            cover.is_not.insert(a);
+            expand(todo, cover, covers);
+        }
+        Nnf::And(g, h) => {
+            todo.push(*g);
+            todo.push(*h);
+            expand(todo, cover, covers);
+        }
+        Nnf::Or(g, h) => {
+            let mut other = todo.clone();
+            other.push(*h);
+            expand(other, cover.clone(), covers);
+            todo.push(*g);
+            expand(todo, cover, covers);
+        }
+        Nnf::Next(g) => {
+            cover.next.insert(*g);
+            expand(todo, cover, covers);
+        }
+        // g U h = h || (g && X(g U h))
+        Nnf::Until(g, h) => {
+            let until = Nnf::Until(g.clone(), h.clone());
+            let mut other = todo.clone();
+            other.push(*h);
+            expand(other, cover.clone(), covers);
+            todo.push(*g);
+            cover.next.insert(until.clone());
+            cover.postponed.insert(until);
+            expand(todo, cover, covers);
+        }
+        // g R h = h && (g || X(g R h))
+        Nnf::Release(g, h) => {
+            let release = Nnf::Release(g.clone(), h.clone());
+            let mut other = todo.clone();
+            other.push(*h.clone());
+            other.push(*g);
+            expand(other, cover.clone(), covers);
+            todo.push(*h);
+            cover.next.insert(release);
+            expand(todo, cover, covers);
+        }
+    }
+}
+
+struct Transition {
+    is: Option<String>,
+    is_not: BTreeSet<String>,
+    target: usize,
+    accepting: BitSet
+}
+
+impl Transition {
+    fn allows(&self, action: &String) -> bool {
+        self.is.as_ref().map_or(true, |a| a == action) && !self.is_not.contains(action)
+    }
+}
+
+// Transition-based generalised Büchi automaton; state 0 is initial and
+// there is an acceptance set for every until subformula.
+struct Buchi {
+    transitions: Vec<Vec<Transition>>,
+    acceptance_sets: usize
+}
+
+fn to_buchi(f: &Nnf) -> Buchi {
+    let mut untils = vec!();
+    collect_untils(f, &mut untils);
+
+    let mut initial = BTreeSet::new();
+    initial.insert(f.clone());
+    let mut states = vec![initial.clone()];
+    let mut ids = HashMap::new();
+    ids.insert(initial, 0);
+    let mut transitions = vec!();
+
+    let mut i = 0;
+    while i < states.len() {
+        let mut covers = vec!();
+        let empty = Cover { is: None, is_not: BTreeSet::new(), next: BTreeSet::new(), postponed: BTreeSet::new() };
+        expand(states[i].iter().cloned().collect(), empty, &mut covers);
+        let mut outgoing = vec!();
+        for cover in covers {
+            let target = match ids.get(&cover.next) {
+                Some(&target) => target,
+                None => states.len()
+            };
+            if target == states.len() {
+                ids.insert(cover.next.clone(), target);
+                states.push(cover.next.clone());
+            }
+            let mut accepting = BitSet::new();
+            for (j, until) in untils.iter().enumerate() {
+                if !cover.postponed.contains(until) {
+                    accepting.insert(j);
+                }
+            }
+            outgoing.push(Transition { is: cover.is, is_not: cover.is_not, target: target, accepting: accepting });
+        }
+        transitions.push(outgoing);
+        i += 1;
+    }
+    Buchi { transitions: transitions, acceptance_sets: untils.len() }
+}
+
+// An infinite run of the LTS: `prefix` leads from an initial state to the
+// start of `cycle`, which can be repeated forever.
+pub struct Lasso {
+    pub prefix: Vec<(u32, String, u32)>,
+    pub cycle: Vec<(u32, String, u32)>
+}
+
+struct Edge {
+    label: String,
+    target: usize,
+    accepting: BitSet
+}
+
+// The label of the step a deadlock state repeats forever; no atom names it,
+// as atoms cannot contain parentheses.
+pub const DEADLOCK: &'static str = "(deadlock)";
+
+// Checks whether every maximal trace from an initial state satisfies `f`.
+// A trace ending in a deadlock is extended by repeating a DEADLOCK step, so
+// that `F a` fails when the model deadlocks before doing an a. The negation
+// of `f` is translated into a Büchi automaton, and an accepting cycle in its
+// product with the LTS is returned as counterexample.
+pub fn check<L: Clone+Copy>(k: &MixedKripkeStructure<L>, f: &LtlFormula) -> Option<Lasso> {
+    let automaton = to_buchi(&nnf(f, true));
+    let actions = k.actions();
+
+    // explore the reachable part of the product
+    let mut vertices: Vec<(u32, usize)> = vec!();
+    let mut ids = HashMap::new();
+    let mut edges: Vec<Vec<Edge>> = vec!();
+    let mut initial = vec!();
+    for s in k.init_states.iter() {
+        let v = (s as u32, 0);
+        if !ids.contains_key(&v) {
+            ids.insert(v, vertices.len());
+            initial.push(vertices.len());
+            vertices.push(v);
+        }
+    }
+    let mut i = 0;
+    while i < vertices.len() {
+        let (s, q) = vertices[i];
+        let mut steps = vec!();
+        for ac in &actions {
+            if let Some(targets) = k.relations.get(&(s, ac.clone())) {
+                if !targets.is_empty() {
+                    steps.push((ac.clone(), targets.iter().collect::<Vec<usize>>()));
+                }
+            }
+        }
+        if steps.is_empty() {
+            steps.push((String::from(DEADLOCK), vec![s as usize]));
+        }
+        let mut outgoing = vec!();
+        for (ac, targets) in steps {
+            for transition in &automaton.transitions[q] {
+                if !transition.allows(&ac) {
+                    continue;
+                }
+                for &t in &targets {
+                    let v = (t as u32, transition.target);
+                    let j = match ids.get(&v) {
+                        Some(&j) => j,
+                        None => vertices.len()
+                    };
+                    if j == vertices.len() {
+                        ids.insert(v, j);
+                        vertices.push(v);
+                    }
+                    outgoing.push(Edge { label: ac.clone(), target: j, accepting: transition.accepting.clone() });
+                }
+            }
+        }
+        edges.push(outgoing);
+        i += 1;
+    }
+
+    // find a strongly connected component whose inner edges visit every acceptance set
+    let successors = edges.iter().map(|out| out.iter().map(|e| e.target).collect()).collect();
+    for component in strongly_connected_components(&successors) {
+        let members = component.iter().cloned().collect::<HashSet<usize>>();
+        let mut accepting = BitSet::new();
+        let mut has_cycle = false;
+        for &v in &component {
+            for e in &edges[v] {
+                if members.contains(&e.target) {
+                    has_cycle = true;
+                    accepting.union_with(&e.accepting);
+                }
+            }
+        }
+        if !has_cycle || accepting.len() < automaton.acceptance_sets {
+            continue;
+        }
+
+        let (prefix, entry) = match initial.iter().find(|v| members.contains(v)) {
+            Some(&v) => (vec!(), v),
+            None => search(&edges, &initial, None, &|e: &Edge| members.contains(&e.target)).unwrap()
+        };
+        let mut cycle = vec!();
+        let mut current = entry;
+        let mut visited = BitSet::new();
+        for j in 0..automaton.acceptance_sets {
+            if visited.contains(j) {
+                continue;
+            }
+            let (path, end) = search(&edges, &vec![current], Some(&members), &|e: &Edge| e.accepting.contains(j)).unwrap();
+            for &(v, e) in &path {
+                visited.union_with(&edges[v][e].accepting);
+            }
+            cycle.extend(path);
+            current = end;
+        }
+        if current != entry || cycle.len() == 0 {
+            let (path, _) = search(&edges, &vec![current], Some(&members), &|e: &Edge| e.target == entry).unwrap();
+            cycle.extend(path);
+        }
+
+        let to_steps = |path: Vec<(usize, usize)>| path.into_iter().map(|(v, e)| {
+            let edge = &edges[v][e];
+            (vertices[v].0, edge.label.clone(), vertices[edge.target].0)
+        }).collect();
+        return Some(Lasso { prefix: to_steps(prefix), cycle: to_steps(cycle) });
+    }
+    None
+}
+
+// Breadth-first search from `from` for the first edge that satisfies
+// `found`, optionally staying `within` a set of vertices; returns the path
+// as (vertex, edge index) pairs together with the vertex it ends in.
+fn search<F>(edges: &Vec<Vec<Edge>>, from: &Vec<usize>, within: Option<&HashSet<usize>>, found: &F) -> Option<(Vec<(usize, usize)>, usize)>
+    where F: Fn(&Edge) -> bool {
+    let mut parent: HashMap<usize, (usize, usize)> = HashMap::new();
+    let mut queue = from.iter().cloned().collect::<VecDeque<usize>>();
+    let mut seen = from.iter().cloned().collect::<HashSet<usize>>();
+    while let Some(v) = queue.pop_front() {
+        for (i, e) in edges[v].iter().enumerate() {
+            if within.map_or(false, |w| !w.contains(&e.target)) {
+                continue;
+            }
+            if found(e) {
+                let mut path = vec![(v, i)];
+                let mut current = v;
+                while let Some(&(u, j)) = parent.get(&current) {
+                    path.push((u, j));
+                    current = u;
+                }
+                path.reverse();
+                return Some((path, e.target));
+            }
+            if !seen.contains(&e.target) {
+                seen.insert(e.target);
+                parent.insert(e.target, (v, i));
+                queue.push_back(e.target);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use algorithms::ltl::{check, Lasso, DEADLOCK};
+    use algorithms::naive;
+    use parsers::ltl::read_ltl_formula;
+    use parsers::mucalculus::read_mu_formula;
+    use parsers::kripke_structure::MixedKripkeStructure;
+    use utils::random::{Random, random_kripke, lts};
+
+    fn counterexample(k: &MixedKripkeStructure<()>, formula: &str) -> Option<Lasso> {
+        let lasso = check(k, &read_ltl_formula(formula).unwrap());
+        if let Some(ref lasso) = lasso {
+            assert_run(k, lasso);
+        }
+        lasso
+    }
+
+    // The lasso starts in an initial state, follows transitions of the model
+    // (or repeats a deadlock) and its cycle returns to where it started.
+    fn assert_run(k: &MixedKripkeStructure<()>, lasso: &Lasso) {
+        let steps = lasso.prefix.iter().chain(lasso.cycle.iter()).collect::<Vec<&(u32, String, u32)>>();
+        assert!(!lasso.cycle.is_empty());
+        assert!(k.init_states.contains(steps[0].0 as usize));
+        for &&(s, ref a, t) in &steps {
+            if a == DEADLOCK {
+                assert_eq!(s, t);
+                assert!(k.relations.iter().all(|(&(u, _), targets)| u != s || targets.is_empty()));
+            } else {
+                assert!(k.relations.get(&(s, a.clone())).map_or(false, |targets| targets.contains(t as usize)), "no step {} -{}-> {}", s, a, t);
+            }
+        }
+        for pair in steps.windows(2) {
+            assert_eq!(pair[0].2, pair[1].0);
+        }
+        assert_eq!(lasso.cycle.last().unwrap().2, lasso.cycle[0].0);
+    }
+
+    #[test]
+    fn infinitely_often() {
+        let k = lts(&[(0, "a", 1), (1, "b", 0)]);
+        assert!(counterexample(&k, "GFa").is_none());
+        assert!(counterexample(&k, "G(a->Xb)").is_none());
+        // after one a, b can be repeated forever
+        let k = lts(&[(0, "b", 0), (0, "a", 1), (1, "b", 1)]);
+        let lasso = counterexample(&k, "GFa").unwrap();
+        assert!(lasso.cycle.iter().all(|&(_, ref a, _)| a == "b"));
+        assert!(counterexample(&k, "FGb").is_none());
+    }
+
+    // the until must be fulfilled on the cycle, not just postponed forever
+    #[test]
+    fn untils_are_fulfilled() {
+        let k = lts(&[(0, "b", 0)]);
+        assert!(counterexample(&k, "G!a").is_none());
+        assert!(counterexample(&k, "bUa").is_some());
+        let k = lts(&[(0, "b", 0), (0, "a", 1), (1, "c", 1)]);
+        let lasso = counterexample(&k, "bUa").unwrap();
+        assert!(lasso.prefix.is_empty() && lasso.cycle == vec!((0, String::from("b"), 0)));
+    }
+
+    #[test]
+    fn deadlocks_repeat() {
+        let k = lts(&[]);
+        let lasso = counterexample(&k, "Fa").unwrap();
+        assert_eq!(lasso.cycle, vec!((0, String::from(DEADLOCK), 0)));
+        assert!(counterexample(&k, "G!a").is_none());
+        let k = lts(&[(0, "a", 1), (0, "b", 2)]);
+        let lasso = counterexample(&k, "Fa").unwrap();
+        assert_eq!(lasso.prefix, vec!((0, String::from("b"), 2)));
+    }
+
+    // F a and G !a against their µ-calculus counterparts on maximal paths
+    #[test]
+    fn agrees_with_mu_calculus() {
+        let actions = vec!(String::from("a"), String::from("b"), String::from("c"));
+        let others = "[b]X && [c]X";
+        let eventually = read_mu_formula(&format!("mu X.(<a>true || <b>true || <c>true) && {}", others)).unwrap();
+        let never = read_mu_formula("nu X.[a]false && [b]X && [c]X").unwrap();
+        for seed in 1..101 {
+            let mut rng = Random::new(seed);
+            let states = 1 + rng.below(6);
+            let transitions = rng.below(2 * states);
+            let k = random_kripke(&mut rng, states, transitions, &actions);
+            let expected = naive::evaluate(&k, eventually.clone(), &[]).unwrap().contains(0);
+            assert_eq!(counterexample(&k, "Fa").is_none(), expected, "F a, seed {}", seed);
+            let expected = naive::evaluate(&k, never.clone(), &[]).unwrap().contains(0);
+            assert_eq!(counterexample(&k, "G!a").is_none(), expected, "G !a, seed {}", seed);
+        }
+    }
+}
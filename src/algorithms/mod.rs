@@ -1,3 +1,24 @@
 pub mod naive;
 pub mod emerson_lei;
-pub mod depth;
\ No newline at end of file
+pub mod depth;
+pub mod simplify;
+pub mod ltl;
+pub mod fairness;
+pub mod parity_game;
+pub mod zielonka;
+pub mod spm;
+pub mod local;
+pub mod evidence;
+pub mod trace;
+pub mod locks;
+pub mod scc;
+pub mod bisimulation;
+pub mod branching;
+pub mod closure;
+pub mod equivalence;
+pub mod distinguish;
+pub mod refinement;
+pub mod simulation;
+pub mod transform;
+pub mod composition;
+pub mod determinise;
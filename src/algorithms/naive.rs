@@ -1,5 +1,6 @@
 use parsers::mucalculus::MuFormula;
 use parsers::kripke_structure::MixedKripkeStructure;
+use algorithms::closure::weak_steps;
 use std::collections::HashSet;
 use std::collections::HashMap;
 use std::hash::Hash;
@@ -8,12 +9,14 @@ use std::{thread, time};
 use bit_set::BitSet;
 
 struct Environment<'time> {
-    map: &'time mut HashMap<String, BitSet>
+    map: &'time mut HashMap<String, BitSet>,
+    weak: HashMap<(u32, String), BitSet>
 }
 
-pub fn evaluate<'time, L: Clone+Copy>(k: &'time MixedKripkeStructure<L>, mu: MuFormula) -> Result<BitSet, MuErrors> {
+pub fn evaluate<'time, L: Clone+Copy>(k: &'time MixedKripkeStructure<L>, mu: MuFormula, internal: &[String]) -> Result<BitSet, MuErrors> {
     let mut env = Environment {
-        map: &mut HashMap::new()
+        map: &mut HashMap::new(),
+        weak: weak_steps(k, &mu, internal)
      };
     return eval(k, &mu, &mut env);
 }
@@ -73,6 +76,25 @@ fn eval<'time, L: Clone+Copy>(k: &'time MixedKripkeStructure<L>, mu: &MuFormula,
             }
             return Ok(result);
         },
+        MuFormula::WeakDiamondOp (p, ref ac, ref f) => {
+            eval(k,
+                &MuFormula::Not(p, box MuFormula::WeakBoxOp(p, ac.clone(), box MuFormula::Not(p, box *f.clone()))),
+            e)
+        },
+        MuFormula::WeakBoxOp (_, ref ac, ref f) => {
+            let states = try!(eval(&k, f, e));
+            let mut result = BitSet::new();
+            for s in k.states.into_iter() {
+                let insert = match e.weak.get(&(s as u32, ac.clone())) {
+                    Some(targets) => targets.is_subset(&states),
+                    None => true
+                };
+                if insert {
+                    result.insert(s as usize);
+                }
+            }
+            return Ok(result);
+        },
 
         // mu calculus
         MuFormula::RecursionValue(_, ref c) => { 
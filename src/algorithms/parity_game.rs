@@ -0,0 +1,397 @@
+use parsers::mucalculus::{MuFormula, renumber};
+use parsers::kripke_structure::MixedKripkeStructure;
+use algorithms::closure::weak_steps;
+use std::collections::{HashMap, VecDeque};
+use bit_set::BitSet;
+
+// Even plays for the formula (the verifier), Odd against it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Player { Even, Odd }
+
+impl Player {
+    pub fn opponent(&self) -> Player {
+        match *self {
+            Player::Even => Player::Odd,
+            Player::Odd => Player::Even
+        }
+    }
+
+    pub fn of_priority(priority: usize) -> Player {
+        if priority % 2 == 0 { Player::Even } else { Player::Odd }
+    }
+}
+
+#[derive(Debug)]
+pub enum GameErrors {
+    VarNotFound(String),
+    NotMonotone(String)
+}
+
+// A subformula of the formula in positive normal form; children are
+// referred to by their position.
+#[derive(Clone, Debug)]
+pub enum Node {
+    Bool(bool),
+    And(usize, usize),
+    Or(usize, usize),
+    DiamondOp(String, usize),
+    BoxOp(String, usize),
+    WeakDiamondOp(String, usize),
+    WeakBoxOp(String, usize),
+    Fixpoint(usize),
+    Variable(usize),
+}
+
+// Vertex 0 is won by Even and vertex 1 by Odd; both only loop to themselves.
+pub const TRUE_VERTEX: usize = 0;
+pub const FALSE_VERTEX: usize = 1;
+
+// A max-parity game: the winner of an infinite play is determined by the
+// parity of the highest priority that occurs infinitely often.
+// Vertices other than the two sinks are pairs of a state and a subformula.
+#[derive(Clone)]
+pub struct ParityGame {
+    pub owner: Vec<Player>,
+    pub priority: Vec<usize>,
+    pub successors: Vec<Vec<usize>>,
+    pub predecessors: Vec<Vec<usize>>,
+    pub labels: Vec<Option<(u32, usize)>>,
+    pub vertices: HashMap<(u32, usize), usize>,
+    // the formula in positive normal form, and its subformulas indexed by position
+    pub formula: MuFormula,
+    pub nodes: Vec<Node>
+}
+
+// The winner of every vertex, and for the vertices owned by their winner a
+// move that keeps winning.
+pub struct Solution {
+    pub winner: Vec<Player>,
+    pub strategy: Vec<Option<usize>>
+}
+
+impl ParityGame {
+    pub fn len(&self) -> usize {
+        self.owner.len()
+    }
+
+    // the states whose vertex for the whole formula is won by Even
+    pub fn satisfying_states(&self, solution: &Solution) -> BitSet {
+        let mut result = BitSet::new();
+        for (&(s, position), &v) in self.vertices.iter() {
+            if position == 0 && solution.winner[v] == Player::Even {
+                result.insert(s as usize);
+            }
+        }
+        result
+    }
+}
+
+// Pushes negations inwards until they only occur in front of variables,
+// dualising operators and binders on the way (`!nu X.f` becomes
+// `mu X.!f[!X/X]`). Bare actions are evaluated as the empty set and are
+// replaced by `false`. For formulas that are monotone in their variables no
+// negation remains.
+fn positive_normal_form(mu: &MuFormula) -> MuFormula {
+    pnf(mu, false, &mut HashMap::new())
+}
+
+fn pnf(mu: &MuFormula, negate: bool, flipped: &mut HashMap<String, bool>) -> MuFormula {
+    match *mu {
+        MuFormula::Action(p, _) => MuFormula::Bool(p, negate),
+        MuFormula::Bool(p, b) => MuFormula::Bool(p, b != negate),
+        MuFormula::RecursionValue(p, ref c) => {
+            if flipped.get(c).cloned().unwrap_or(false) == negate {
+                MuFormula::RecursionValue(p, c.clone())
+            } else {
+                MuFormula::Not(p, box MuFormula::RecursionValue(p, c.clone()))
+            }
+        }
+        MuFormula::Not(_, ref f) => pnf(f, !negate, flipped),
+        MuFormula::And(p, ref f, ref g) | MuFormula::Or(p, ref f, ref g) => {
+            let conjunction = match *mu { MuFormula::And(_, _, _) => !negate, _ => negate };
+            let f = pnf(f, negate, flipped);
+            let g = pnf(g, negate, flipped);
+            if conjunction { MuFormula::And(p, box f, box g) } else { MuFormula::Or(p, box f, box g) }
+        }
+        MuFormula::DiamondOp (p, ref ac, ref f) | MuFormula::BoxOp (p, ref ac, ref f) => {
+            let diamond = match *mu { MuFormula::DiamondOp(_, _, _) => !negate, _ => negate };
+            let f = pnf(f, negate, flipped);
+            if diamond { MuFormula::DiamondOp(p, ac.clone(), box f) } else { MuFormula::BoxOp(p, ac.clone(), box f) }
+        }
+        MuFormula::WeakDiamondOp (p, ref ac, ref f) | MuFormula::WeakBoxOp (p, ref ac, ref f) => {
+            let diamond = match *mu { MuFormula::WeakDiamondOp(_, _, _) => !negate, _ => negate };
+            let f = pnf(f, negate, flipped);
+            if diamond { MuFormula::WeakDiamondOp(p, ac.clone(), box f) } else { MuFormula::WeakBoxOp(p, ac.clone(), box f) }
+        }
+        MuFormula::Mu(p, ref c, ref f) | MuFormula::Nu(p, ref c, ref f) => {
+            let least = match *mu { MuFormula::Mu(_, _, _) => !negate, _ => negate };
+            let previous = flipped.insert(c.clone(), negate);
+            let f = pnf(f, negate, flipped);
+            match previous {
+                Some(b) => flipped.insert(c.clone(), b),
+                None => flipped.remove(c)
+            };
+            if least { MuFormula::Mu(p, c.clone(), box f) } else { MuFormula::Nu(p, c.clone(), box f) }
+        }
+    }
+}
+
+// Flattens the formula into nodes, numbered in the same pre-order as the
+// positions `renumber` assigns, and computes the priority of each fixpoint.
+// Binders that are nested under binders of the other sign get a lower
+// priority than those binders; nu binders get even priorities and mu binders
+// odd ones.
+fn compile(mu: &MuFormula) -> Result<(MuFormula, Vec<Node>, HashMap<usize, usize>), GameErrors> {
+    let pnf = renumber(&positive_normal_form(mu));
+    let mut nodes = vec!();
+    let mut levels = vec!();
+    try!(flatten(&pnf, &mut vec!(), None, &mut nodes, &mut levels));
+    let max_level = levels.iter().map(|&(_, level, _)| level).max().unwrap_or(0);
+    let mut priorities = HashMap::new();
+    for (p, level, greatest) in levels {
+        priorities.insert(p, 2 * (max_level - level) + if greatest { 0 } else { 1 });
+    }
+    Ok((pnf, nodes, priorities))
+}
+
+fn flatten(mu: &MuFormula, scope: &mut Vec<(String, usize)>, surrounding: Option<(bool, usize)>, nodes: &mut Vec<Node>, levels: &mut Vec<(usize, usize, bool)>) -> Result<usize, GameErrors> {
+    let p = nodes.len();
+    nodes.push(Node::Bool(false));
+    let node = match *mu {
+        MuFormula::Action(_, _) => Node::Bool(false),
+        MuFormula::Bool(_, b) => Node::Bool(b),
+        MuFormula::Not(_, ref f) => return Err(GameErrors::NotMonotone(f.to_string())),
+        MuFormula::RecursionValue(_, ref c) => {
+            match scope.iter().rev().find(|&&(ref v, _)| v == c) {
+                Some(&(_, binder)) => Node::Variable(binder),
+                None => return Err(GameErrors::VarNotFound(c.clone()))
+            }
+        }
+        MuFormula::And(_, ref f, ref g) => {
+            let f = try!(flatten(f, scope, surrounding, nodes, levels));
+            let g = try!(flatten(g, scope, surrounding, nodes, levels));
+            Node::And(f, g)
+        }
+        MuFormula::Or(_, ref f, ref g) => {
+            let f = try!(flatten(f, scope, surrounding, nodes, levels));
+            let g = try!(flatten(g, scope, surrounding, nodes, levels));
+            Node::Or(f, g)
+        }
+        MuFormula::DiamondOp (_, ref ac, ref f) => Node::DiamondOp(ac.clone(), try!(flatten(f, scope, surrounding, nodes, levels))),
+        MuFormula::BoxOp (_, ref ac, ref f) => Node::BoxOp(ac.clone(), try!(flatten(f, scope, surrounding, nodes, levels))),
+        MuFormula::WeakDiamondOp (_, ref ac, ref f) => Node::WeakDiamondOp(ac.clone(), try!(flatten(f, scope, surrounding, nodes, levels))),
+        MuFormula::WeakBoxOp (_, ref ac, ref f) => Node::WeakBoxOp(ac.clone(), try!(flatten(f, scope, surrounding, nodes, levels))),
+        MuFormula::Mu(_, ref c, ref f) | MuFormula::Nu(_, ref c, ref f) => {
+            let greatest = match *mu { MuFormula::Nu(_, _, _) => true, _ => false };
+            let level = match surrounding {
+                Some((sign, level)) => if sign == greatest { level } else { level + 1 },
+                None => 0
+            };
+            levels.push((p, level, greatest));
+            scope.push((c.clone(), p));
+            let f = try!(flatten(f, scope, Some((greatest, level)), nodes, levels));
+            scope.pop();
+            Node::Fixpoint(f)
+        }
+    };
+    nodes[p] = node;
+    Ok(p)
+}
+
+// Builds the game for a formula on an LTS by exploring vertices from a set
+// of seeds. Exploration can be bounded, which leaves some vertices without
+// successors; `is_expanded` tells those apart.
+pub struct GameBuilder<'a, L: 'a + Clone+Copy> {
+    kripke: &'a MixedKripkeStructure<L>,
+    weak: HashMap<(u32, String), BitSet>,
+    priorities: HashMap<usize, usize>,
+    frontier: VecDeque<usize>,
+    expanded: BitSet,
+    pub game: ParityGame
+}
+
+impl<'a, L: Clone+Copy> GameBuilder<'a, L> {
+    pub fn new(k: &'a MixedKripkeStructure<L>, mu: &MuFormula, internal: &[String]) -> Result<GameBuilder<'a, L>, GameErrors> {
+        let (formula, nodes, priorities) = try!(compile(mu));
+        let weak = weak_steps(k, &formula, internal);
+        let mut builder = GameBuilder {
+            kripke: k,
+            weak: weak,
+            priorities: priorities,
+            frontier: VecDeque::new(),
+            expanded: BitSet::new(),
+            game: ParityGame {
+                owner: vec![Player::Even, Player::Even],
+                priority: vec![0, 1],
+                successors: vec![vec![TRUE_VERTEX], vec![FALSE_VERTEX]],
+                predecessors: vec![vec![TRUE_VERTEX], vec![FALSE_VERTEX]],
+                labels: vec![None, None],
+                vertices: HashMap::new(),
+                formula: formula,
+                nodes: nodes
+            }
+        };
+        builder.expanded.insert(TRUE_VERTEX);
+        builder.expanded.insert(FALSE_VERTEX);
+        Ok(builder)
+    }
+
+    // the vertex for `s` and the whole formula
+    pub fn seed(&mut self, s: u32) -> usize {
+        self.vertex(s, 0)
+    }
+
+    pub fn is_expanded(&self, v: usize) -> bool {
+        self.expanded.contains(v)
+    }
+
+    // number of vertices expanded so far, the sinks included
+    pub fn explored(&self) -> usize {
+        self.expanded.len()
+    }
+
+    fn vertex(&mut self, s: u32, position: usize) -> usize {
+        if let Some(&v) = self.game.vertices.get(&(s, position)) {
+            return v;
+        }
+        let (owner, priority) = match self.game.nodes[position] {
+            Node::And(_, _) | Node::BoxOp(_, _) | Node::WeakBoxOp(_, _) => (Player::Odd, 0),
+            Node::Fixpoint(_) => (Player::Even, self.priorities[&position]),
+            _ => (Player::Even, 0)
+        };
+        let v = self.game.owner.len();
+        self.game.owner.push(owner);
+        self.game.priority.push(priority);
+        self.game.successors.push(vec!());
+        self.game.predecessors.push(vec!());
+        self.game.labels.push(Some((s, position)));
+        self.game.vertices.insert((s, position), v);
+        self.frontier.push_back(v);
+        v
+    }
+
+    fn add_move(&mut self, v: usize, w: usize) {
+        self.game.successors[v].push(w);
+        self.game.predecessors[w].push(v);
+    }
+
+    // Expands at most `limit` vertices (all when None); returns whether the
+    // game is complete.
+    pub fn explore(&mut self, limit: Option<usize>) -> bool {
+        let mut expanded = 0;
+        while limit.map_or(true, |limit| expanded < limit) {
+            let v = match self.frontier.pop_front() {
+                Some(v) => v,
+                None => return true
+            };
+            expanded += 1;
+            self.expanded.insert(v);
+            let (s, position) = self.game.labels[v].unwrap();
+            let node = self.game.nodes[position].clone();
+            let targets = match node {
+                Node::Bool(b) => vec![if b { TRUE_VERTEX } else { FALSE_VERTEX }],
+                Node::And(f, g) | Node::Or(f, g) => vec![self.vertex(s, f), self.vertex(s, g)],
+                Node::Fixpoint(f) => vec![self.vertex(s, f)],
+                Node::Variable(binder) => vec![self.vertex(s, binder)],
+                Node::DiamondOp(ref ac, f) | Node::BoxOp(ref ac, f) |
+                Node::WeakDiamondOp(ref ac, f) | Node::WeakBoxOp(ref ac, f) => {
+                    let states = {
+                        let relations = match node {
+                            Node::WeakDiamondOp(_, _) | Node::WeakBoxOp(_, _) => &self.weak,
+                            _ => &self.kripke.relations
+                        };
+                        relations.get(&(s, ac.clone())).map(|t| t.iter().collect::<Vec<usize>>()).unwrap_or(vec!())
+                    };
+                    if states.is_empty() {
+                        // Even is stuck on a diamond, Odd on a box
+                        vec![if self.game.owner[v] == Player::Even { FALSE_VERTEX } else { TRUE_VERTEX }]
+                    } else {
+                        states.into_iter().map(|t| self.vertex(t as u32, f)).collect()
+                    }
+                }
+            };
+            for w in targets {
+                self.add_move(v, w);
+            }
+        }
+        self.frontier.is_empty()
+    }
+}
+
+// The complete game for all states of `k`.
+pub fn build<L: Clone+Copy>(k: &MixedKripkeStructure<L>, mu: &MuFormula, internal: &[String]) -> Result<ParityGame, GameErrors> {
+    let mut builder = try!(GameBuilder::new(k, mu, internal));
+    for s in k.states.iter() {
+        builder.seed(s as u32);
+    }
+    builder.explore(None);
+    Ok(builder.game)
+}
+
+// Vertices in `within` from which `player` can force the play into `target`,
+// together with the moves that do so.
+pub fn attractor(game: &ParityGame, player: Player, target: &BitSet, within: &BitSet, strategy: &mut Vec<Option<usize>>) -> BitSet {
+    let mut result = target.intersection(within).collect::<BitSet>();
+    let mut count: HashMap<usize, usize> = HashMap::new();
+    let mut queue = result.iter().collect::<VecDeque<usize>>();
+    while let Some(w) = queue.pop_front() {
+        for &v in &game.predecessors[w] {
+            if !within.contains(v) || result.contains(v) {
+                continue;
+            }
+            if game.owner[v] == player {
+                result.insert(v);
+                strategy[v] = Some(w);
+                queue.push_back(v);
+            } else {
+                let remaining = count.entry(v).or_insert_with(|| {
+                    game.successors[v].iter().filter(|&&u| within.contains(u)).count()
+                });
+                *remaining -= 1;
+                if *remaining == 0 {
+                    result.insert(v);
+                    queue.push_back(v);
+                }
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use algorithms::naive;
+    use algorithms::parity_game::positive_normal_form;
+    use parsers::mucalculus::MuFormula;
+    use utils::random::{Random, random_formula, random_kripke};
+
+    fn negations(mu: &MuFormula) -> usize {
+        match *mu {
+            MuFormula::Not(_, ref f) => 1 + negations(f),
+            MuFormula::DiamondOp(_, _, ref f) | MuFormula::BoxOp(_, _, ref f) |
+            MuFormula::WeakDiamondOp(_, _, ref f) | MuFormula::WeakBoxOp(_, _, ref f) |
+            MuFormula::Mu(_, _, ref f) | MuFormula::Nu(_, _, ref f) => negations(f),
+            MuFormula::And(_, ref f, ref g) | MuFormula::Or(_, ref f, ref g) => negations(f) + negations(g),
+            MuFormula::Action(_, _) | MuFormula::Bool(_, _) | MuFormula::RecursionValue(_, _) => 0
+        }
+    }
+
+    // The generator only negates closed formulas, so every negation can be
+    // pushed through to the leaves.
+    #[test]
+    fn positive_normal_form_keeps_meaning() {
+        let actions = vec!(String::from("a"), String::from("b"), String::from("tau"));
+        for seed in 1..11 {
+            let mut rng = Random::new(seed);
+            for _ in 0..50 {
+                let states = 1 + rng.below(8);
+                let transitions = rng.below(3 * states);
+                let k = random_kripke(&mut rng, states, transitions, &actions);
+                let f = random_formula(&mut rng, 6, &actions, true);
+                let pnf = positive_normal_form(&f);
+                assert_eq!(negations(&pnf), 0, "{} became {}", f.to_string(), pnf.to_string());
+                assert_eq!(naive::evaluate(&k, pnf, &[]).unwrap(), naive::evaluate(&k, f.clone(), &[]).unwrap(), "{}", f.to_string());
+            }
+        }
+    }
+}
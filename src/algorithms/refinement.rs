@@ -0,0 +1,147 @@
+use parsers::kripke_structure::MixedKripkeStructure;
+use algorithms::closure::{tau_closure, weak_successors};
+use std::collections::{HashMap, HashSet, VecDeque};
+use bit_set::BitSet;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Model {
+    Traces,
+    Failures
+}
+
+// A trace of the implementation that the specification does not allow, or
+// after which the implementation can stably refuse a set of actions that
+// the specification cannot refuse.
+pub struct Counterexample {
+    pub trace: Vec<String>,
+    pub refusal: Option<Vec<String>>
+}
+
+// the visible actions enabled in s
+fn initials<L: Clone+Copy>(k: &MixedKripkeStructure<L>, s: usize, visible: &[String]) -> Vec<String> {
+    visible.iter().filter(|a| k.relations.get(&(s as u32, (*a).clone())).map_or(false, |t| !t.is_empty())).cloned().collect()
+}
+
+fn stable<L: Clone+Copy>(k: &MixedKripkeStructure<L>, s: usize, internal: &[String]) -> bool {
+    internal.iter().all(|a| k.relations.get(&(s as u32, a.clone())).map_or(true, |t| t.is_empty()))
+}
+
+// A pair of an implementation state and a set of specification states, with
+// the pair it was reached from and the visible action, if any.
+type Parents = HashMap<(usize, BitSet), ((usize, BitSet), Option<String>)>;
+
+fn trace_to(parent: &Parents, pair: &(usize, BitSet)) -> Vec<String> {
+    let mut trace = vec!();
+    let mut current = pair.clone();
+    while let Some(&(ref previous, ref action)) = parent.get(&current) {
+        if let Some(ref a) = *action {
+            trace.push(a.clone());
+        }
+        current = previous.clone();
+    }
+    trace.reverse();
+    trace
+}
+
+// Checks whether `implementation` refines `spec` in the given model. The
+// specification is normalised on the fly: the implementation is explored
+// together with the set of specification states reachable by the same
+// trace, which is empty exactly when the trace is not allowed. Pairs are
+// explored breadth first, so the counterexample has a shortest trace.
+pub fn refines<L: Clone+Copy>(spec: &MixedKripkeStructure<L>, implementation: &MixedKripkeStructure<L>, model: Model, internal: &[String]) -> Option<Counterexample> {
+    let closure = tau_closure(spec, internal);
+    let mut visible = spec.actions();
+    visible.extend(implementation.actions());
+    visible.sort();
+    visible.dedup();
+    visible.retain(|a| !internal.contains(a));
+
+    let mut parent: Parents = HashMap::new();
+    let mut visited = HashSet::new();
+    let mut queue = VecDeque::new();
+    for i in implementation.init_states.iter() {
+        let start = (i, weak_successors(spec, &closure, &spec.init_states, None));
+        if visited.insert(start.clone()) {
+            queue.push_back(start);
+        }
+    }
+
+    while let Some(pair) = queue.pop_front() {
+        let (i, ref normal) = pair;
+        if model == Model::Failures && stable(implementation, i, internal) {
+            let enabled = initials(implementation, i, &visible);
+            let allowed = normal.iter().any(|s| {
+                stable(spec, s, internal) && initials(spec, s, &visible).iter().all(|a| enabled.contains(a))
+            });
+            if !allowed {
+                let refusal = visible.iter().filter(|a| !enabled.contains(a)).cloned().collect();
+                return Some(Counterexample { trace: trace_to(&parent, &pair), refusal: Some(refusal) });
+            }
+        }
+        let mut steps = vec!();
+        for a in internal {
+            if let Some(targets) = implementation.relations.get(&(i as u32, a.clone())) {
+                steps.extend(targets.iter().map(|j| ((j, normal.clone()), None)));
+            }
+        }
+        for a in &visible {
+            if let Some(targets) = implementation.relations.get(&(i as u32, a.clone())) {
+                if targets.is_empty() {
+                    continue;
+                }
+                let next = weak_successors(spec, &closure, normal, Some(a));
+                if next.is_empty() {
+                    let mut trace = trace_to(&parent, &pair);
+                    trace.push(a.clone());
+                    return Some(Counterexample { trace: trace, refusal: None });
+                }
+                steps.extend(targets.iter().map(|j| ((j, next.clone()), Some(a.clone()))));
+            }
+        }
+        for (next, action) in steps {
+            if visited.insert(next.clone()) {
+                parent.insert(next.clone(), (pair.clone(), action));
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use algorithms::refinement::{refines, Model};
+    use utils::random::lts;
+
+    #[test]
+    fn early_and_late_choice() {
+        let tau = vec!(String::from("tau"));
+        // a.b + a.c and a.(b + c)
+        let early = lts(&[(0, "a", 1), (1, "b", 2), (0, "a", 3), (3, "c", 4)]);
+        let late = lts(&[(0, "a", 1), (1, "b", 2), (1, "c", 3)]);
+        assert!(refines(&early, &late, Model::Traces, &tau).is_none());
+        assert!(refines(&late, &early, Model::Traces, &tau).is_none());
+        // the late choice refuses less, so only it refines the early choice
+        assert!(refines(&early, &late, Model::Failures, &tau).is_none());
+        let counterexample = refines(&late, &early, Model::Failures, &tau).unwrap();
+        assert_eq!(counterexample.trace, vec!(String::from("a")));
+        let refusal = counterexample.refusal.unwrap();
+        assert!(refusal.contains(&String::from("b")) || refusal.contains(&String::from("c")));
+    }
+
+    #[test]
+    fn traces_and_internal_choice() {
+        let tau = vec!(String::from("tau"));
+        let ab = lts(&[(0, "a", 1), (1, "b", 2)]);
+        let ac = lts(&[(0, "a", 1), (1, "c", 2)]);
+        let counterexample = refines(&ab, &ac, Model::Traces, &tau).unwrap();
+        assert_eq!(counterexample.trace, vec!(String::from("a"), String::from("c")));
+        assert!(counterexample.refusal.is_none());
+        // tau.a + tau.b refines a + b in traces but may refuse either
+        let internal = lts(&[(0, "tau", 1), (1, "a", 2), (0, "tau", 3), (3, "b", 4)]);
+        let external = lts(&[(0, "a", 1), (0, "b", 2)]);
+        assert!(refines(&external, &internal, Model::Traces, &tau).is_none());
+        assert!(refines(&external, &internal, Model::Failures, &tau).is_some());
+        assert!(refines(&internal, &external, Model::Failures, &tau).is_none());
+    }
+}
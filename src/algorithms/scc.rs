@@ -0,0 +1,73 @@
+use parsers::kripke_structure::MixedKripkeStructure;
+use utils::graph::strongly_connected_components;
+use std::collections::HashMap;
+use bit_set::BitSet;
+
+// The strongly connected components of (part of) an LTS and the graph
+// between them. Components are numbered in reverse topological order, so
+// edges of the condensation only go to components with a lower number.
+pub struct Decomposition {
+    pub components: Vec<BitSet>,
+    // the component of each state
+    pub component_of: HashMap<usize, usize>,
+    // whether a component contains a cycle, i.e. it is not a single state
+    // without a self loop
+    pub nontrivial: Vec<bool>,
+    // successors of each component in the condensation, without duplicates
+    pub condensation: Vec<Vec<usize>>
+}
+
+impl Decomposition {
+    // components without edges to other components
+    pub fn bottom(&self) -> Vec<usize> {
+        (0..self.components.len()).filter(|&c| self.condensation[c].is_empty()).collect()
+    }
+}
+
+// Decomposes the states in `within` using the transitions between them,
+// only those labelled with `actions` when given.
+pub fn decompose<L: Clone+Copy>(k: &MixedKripkeStructure<L>, within: &BitSet, actions: Option<&[String]>) -> Decomposition {
+    let states = within.iter().collect::<Vec<usize>>();
+    let index = states.iter().enumerate().map(|(i, &s)| (s, i)).collect::<HashMap<usize, usize>>();
+    let mut successors = vec![vec!(); states.len()];
+    for (&(s, ref a), targets) in k.relations.iter() {
+        if actions.map_or(false, |actions| !actions.contains(a)) {
+            continue;
+        }
+        if let Some(&i) = index.get(&(s as usize)) {
+            successors[i].extend(targets.iter().filter_map(|t| index.get(&t).cloned()));
+        }
+    }
+
+    let sccs = strongly_connected_components(&successors);
+    let mut component_of = HashMap::new();
+    for (c, component) in sccs.iter().enumerate() {
+        for &i in component {
+            component_of.insert(states[i], c);
+        }
+    }
+    let mut nontrivial = vec!();
+    let mut condensation = vec!();
+    for (c, component) in sccs.iter().enumerate() {
+        let mut cycle = component.len() > 1;
+        let mut next = BitSet::new();
+        for &i in component {
+            for &j in &successors[i] {
+                let d = component_of[&states[j]];
+                if d == c {
+                    cycle = true;
+                } else {
+                    next.insert(d);
+                }
+            }
+        }
+        nontrivial.push(cycle);
+        condensation.push(next.iter().collect());
+    }
+    Decomposition {
+        components: sccs.into_iter().map(|component| component.into_iter().map(|i| states[i]).collect::<BitSet>()).collect(),
+        component_of: component_of,
+        nontrivial: nontrivial,
+        condensation: condensation
+    }
+}
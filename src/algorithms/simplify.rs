@@ -0,0 +1,259 @@
+use parsers::mucalculus::{MuFormula, occurs_free};
+
+// Rewrites a formula bottom-up into an equivalent, smaller one:
+// - boolean constant folding (`true && f`, `false || f`, `!!f`, `<a>false`, `[a]true`, and likewise for weak modalities)
+// - vacuous binder removal (`mu X.f` where X does not occur in f)
+// - fixpoint merging (`mu X.mu Y.f` becomes `mu X.f[X/Y]`, likewise for nu)
+// - modality distribution (`<a>f || <a>g` becomes `<a>(f || g)`, `[a]f && [a]g` becomes `[a](f && g)`)
+// Positions of the surviving nodes are kept, so the result can be evaluated directly.
+pub fn simplify(mu: &MuFormula) -> MuFormula {
+    match *mu {
+        MuFormula::Action(_, _) | MuFormula::Bool(_, _) | MuFormula::RecursionValue(_, _) => {
+            return mu.clone();
+        }
+        MuFormula::Not(p, ref f) => {
+            return simplify_not(p, simplify(f));
+        }
+        MuFormula::And(p, ref f, ref g) => {
+            return simplify_and(p, simplify(f), simplify(g));
+        }
+        MuFormula::Or(p, ref f, ref g) => {
+            return simplify_or(p, simplify(f), simplify(g));
+        }
+        MuFormula::DiamondOp (p, ref ac, ref f) => {
+            return match simplify(f) {
+                MuFormula::Bool(_, false) => MuFormula::Bool(p, false),
+                sf => MuFormula::DiamondOp(p, ac.clone(), box sf)
+            };
+        }
+        MuFormula::BoxOp (p, ref ac, ref f) => {
+            return match simplify(f) {
+                MuFormula::Bool(_, true) => MuFormula::Bool(p, true),
+                sf => MuFormula::BoxOp(p, ac.clone(), box sf)
+            };
+        }
+        MuFormula::WeakDiamondOp (p, ref ac, ref f) => {
+            return match simplify(f) {
+                MuFormula::Bool(_, false) => MuFormula::Bool(p, false),
+                sf => MuFormula::WeakDiamondOp(p, ac.clone(), box sf)
+            };
+        }
+        MuFormula::WeakBoxOp (p, ref ac, ref f) => {
+            return match simplify(f) {
+                MuFormula::Bool(_, true) => MuFormula::Bool(p, true),
+                sf => MuFormula::WeakBoxOp(p, ac.clone(), box sf)
+            };
+        }
+        MuFormula::Mu(p, ref c, ref f) => {
+            return simplify_fixpoint(p, c, simplify(f), false);
+        }
+        MuFormula::Nu(p, ref c, ref f) => {
+            return simplify_fixpoint(p, c, simplify(f), true);
+        }
+    }
+}
+
+fn simplify_not(p: usize, f: MuFormula) -> MuFormula {
+    match f {
+        MuFormula::Bool(_, b) => MuFormula::Bool(p, !b),
+        MuFormula::Not(_, g) => *g,
+        f => MuFormula::Not(p, box f)
+    }
+}
+
+fn simplify_and(p: usize, f: MuFormula, g: MuFormula) -> MuFormula {
+    match (f, g) {
+        (MuFormula::Bool(_, true), g) => g,
+        (f, MuFormula::Bool(_, true)) => f,
+        (MuFormula::Bool(_, false), _) | (_, MuFormula::Bool(_, false)) => MuFormula::Bool(p, false),
+        (MuFormula::BoxOp(p2, ref a, ref f), MuFormula::BoxOp(_, ref b, ref g)) if a == b => {
+            match simplify_and(p2, *f.clone(), *g.clone()) {
+                MuFormula::Bool(_, true) => MuFormula::Bool(p, true),
+                fg => MuFormula::BoxOp(p, a.clone(), box fg)
+            }
+        }
+        (f, g) => MuFormula::And(p, box f, box g)
+    }
+}
+
+fn simplify_or(p: usize, f: MuFormula, g: MuFormula) -> MuFormula {
+    match (f, g) {
+        (MuFormula::Bool(_, false), g) => g,
+        (f, MuFormula::Bool(_, false)) => f,
+        (MuFormula::Bool(_, true), _) | (_, MuFormula::Bool(_, true)) => MuFormula::Bool(p, true),
+        (MuFormula::DiamondOp(p2, ref a, ref f), MuFormula::DiamondOp(_, ref b, ref g)) if a == b => {
+            match simplify_or(p2, *f.clone(), *g.clone()) {
+                MuFormula::Bool(_, false) => MuFormula::Bool(p, false),
+                fg => MuFormula::DiamondOp(p, a.clone(), box fg)
+            }
+        }
+        (f, g) => MuFormula::Or(p, box f, box g)
+    }
+}
+
+fn simplify_fixpoint(p: usize, c: &String, f: MuFormula, greatest: bool) -> MuFormula {
+    // the least fixpoint of the identity is the empty set, the greatest all states
+    match f {
+        MuFormula::RecursionValue(_, ref c2) if c2 == c => {
+            return MuFormula::Bool(p, greatest);
+        }
+        _ => {}
+    }
+    if !occurs_free(c, &f) {
+        return f;
+    }
+    let merged = match f {
+        MuFormula::Mu(_, ref c2, ref g) if !greatest => rename_free(g, c2, c),
+        MuFormula::Nu(_, ref c2, ref g) if greatest => rename_free(g, c2, c),
+        _ => None
+    };
+    match merged {
+        Some(g) => simplify_fixpoint(p, c, g, greatest),
+        None => {
+            if greatest {
+                MuFormula::Nu(p, c.clone(), box f)
+            } else {
+                MuFormula::Mu(p, c.clone(), box f)
+            }
+        }
+    }
+}
+
+// Replaces the free occurrences of `from` by `to`. Returns None when an
+// occurrence would be captured by a binder of `to` inside `mu`.
+fn rename_free(mu: &MuFormula, from: &String, to: &String) -> Option<MuFormula> {
+    if !occurs_free(from, mu) {
+        return Some(mu.clone());
+    }
+    match *mu {
+        MuFormula::RecursionValue(p, _) => {
+            return Some(MuFormula::RecursionValue(p, to.clone()));
+        }
+        MuFormula::Mu(p, ref c, ref f) => {
+            if c == to {
+                return None;
+            }
+            return rename_free(f, from, to).map(|g| MuFormula::Mu(p, c.clone(), box g));
+        }
+        MuFormula::Nu(p, ref c, ref f) => {
+            if c == to {
+                return None;
+            }
+            return rename_free(f, from, to).map(|g| MuFormula::Nu(p, c.clone(), box g));
+        }
+        MuFormula::Not(p, ref f) => {
+            return rename_free(f, from, to).map(|g| MuFormula::Not(p, box g));
+        }
+        MuFormula::DiamondOp (p, ref ac, ref f) => {
+            return rename_free(f, from, to).map(|g| MuFormula::DiamondOp(p, ac.clone(), box g));
+        }
+        MuFormula::BoxOp (p, ref ac, ref f) => {
+            return rename_free(f, from, to).map(|g| MuFormula::BoxOp(p, ac.clone(), box g));
+        }
+        MuFormula::WeakDiamondOp (p, ref ac, ref f) => {
+            return rename_free(f, from, to).map(|g| MuFormula::WeakDiamondOp(p, ac.clone(), box g));
+        }
+        MuFormula::WeakBoxOp (p, ref ac, ref f) => {
+            return rename_free(f, from, to).map(|g| MuFormula::WeakBoxOp(p, ac.clone(), box g));
+        }
+        MuFormula::And(p, ref f, ref g) => {
+            return match (rename_free(f, from, to), rename_free(g, from, to)) {
+                (Some(f), Some(g)) => Some(MuFormula::And(p, box f, box g)),
+                _ => None
+            };
+        }
+        MuFormula::Or(p, ref f, ref g) => {
+            return match (rename_free(f, from, to), rename_free(g, from, to)) {
+                (Some(f), Some(g)) => Some(MuFormula::Or(p, box f, box g)),
+                _ => None
+            };
+        }
+        MuFormula::Action(_, _) | MuFormula::Bool(_, _) => {
+            return Some(mu.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use algorithms::{naive, emerson_lei};
+    use algorithms::simplify::simplify;
+    use parsers::mucalculus::{MuFormula, read_mu_formula};
+    use parsers::kripke_structure::MixedKripkeStructure;
+    use utils::random::{Random, random_formula, random_kripke};
+
+    fn models() -> Vec<MixedKripkeStructure<()>> {
+        let actions = vec!(String::from("a"), String::from("b"), String::from("tau"));
+        let mut rng = Random::new(1);
+        let mut models = vec!();
+        for _ in 0..20 {
+            let states = 1 + rng.below(6);
+            let transitions = rng.below(3 * states);
+            models.push(random_kripke(&mut rng, states, transitions, &actions));
+        }
+        models
+    }
+
+    fn assert_equivalent(f: &MuFormula, g: &MuFormula) {
+        for k in models() {
+            assert_eq!(naive::evaluate(&k, g.clone(), &[]).unwrap(), naive::evaluate(&k, f.clone(), &[]).unwrap(), "{} became {}", f.to_string(), g.to_string());
+        }
+    }
+
+    #[test]
+    fn rewrites_preserve_meaning() {
+        let formulas = [
+            "true && <a>true || false",
+            "!!<a>[b]false",
+            "<a>true || <a><b>true",
+            "[a]false && [a]<b>true",
+            "<tau>false || [b]true",
+            "mu X.<a>true",
+            "mu X.X || nu Y.Y",
+            "mu X.mu Y.(<a>X || <b>Y || [tau]false)",
+            "nu X.nu Y.(<a>X && [b]Y)",
+            "nu X.nu Y.nu Z.([a]X && [b]Y && [tau]Z && <a>true)",
+            "mu X.(<a>X || mu Y.(<b>Y || <tau>X))",
+            "nu X.mu Y.nu Z.(<a>X || <b>Y && [tau]Z)",
+            "!nu X.([a]X && <b>true)",
+            "!mu X.(<b>true || <a>X) && [a]false"
+        ];
+        for s in formulas.iter() {
+            let f = read_mu_formula(s).unwrap();
+            assert_equivalent(&f, &simplify(&f));
+        }
+    }
+
+    // Merging `mu Y` into `mu X` would capture the occurrence of Y under the
+    // inner binder of X, so the binders are kept apart. The naive evaluator
+    // does not support shadowing, so this one is evaluated with Emerson-Lei.
+    #[test]
+    fn merging_avoids_capture() {
+        let f = read_mu_formula("mu X.mu Y.(<a>X || nu X.(<b>Y && [a]X))").unwrap();
+        let g = simplify(&f);
+        let merged = match g {
+            MuFormula::Mu(_, _, ref body) => match **body { MuFormula::Mu(_, _, _) => false, _ => true },
+            _ => true
+        };
+        assert!(!merged, "binders were merged: {}", g.to_string());
+        for k in models() {
+            assert_eq!(emerson_lei::evaluate(&k, g.clone(), &[]).unwrap(), emerson_lei::evaluate(&k, f.clone(), &[]).unwrap());
+        }
+    }
+
+    #[test]
+    fn random_formulas_keep_meaning() {
+        let actions = vec!(String::from("a"), String::from("b"), String::from("tau"));
+        for seed in 1..11 {
+            let mut rng = Random::new(seed);
+            for _ in 0..50 {
+                let states = 1 + rng.below(8);
+                let transitions = rng.below(3 * states);
+                let k = random_kripke(&mut rng, states, transitions, &actions);
+                let f = random_formula(&mut rng, 6, &actions, true);
+                let expected = naive::evaluate(&k, f.clone(), &[]).unwrap();
+                assert_eq!(naive::evaluate(&k, simplify(&f), &[]).unwrap(), expected, "{} became {}", f.to_string(), simplify(&f).to_string());
+            }
+        }
+    }
+}
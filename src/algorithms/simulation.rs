@@ -0,0 +1,146 @@
+use parsers::kripke_structure::MixedKripkeStructure;
+use algorithms::bisimulation::Transitions;
+use algorithms::equivalence::disjoint_union;
+use std::collections::{HashMap, VecDeque};
+use bit_set::BitSet;
+
+// The simulation preorder, as the set of states simulating each state (by
+// index), computed with the algorithm of Henzinger, Henzinger and Kopke
+// adapted to labelled transitions. Instead of keeping a counter per
+// (action, state, state) triple, whether a state still has a step into a
+// simulating set is checked on the bit sets directly.
+pub fn simulation_preorder(t: &Transitions) -> Vec<BitSet> {
+    let n = t.len();
+    let actions = t.actions.len();
+    let mut post = vec![vec![BitSet::new(); n]; actions];
+    let mut pre = vec![vec![vec!(); n]; actions];
+    let mut enabled = vec![BitSet::new(); n];
+    let mut can = vec![BitSet::new(); actions];
+    for i in 0..n {
+        for &(a, j) in &t.outgoing[i] {
+            post[a][i].insert(j);
+            pre[a][j].push(i);
+            enabled[i].insert(a);
+            can[a].insert(i);
+        }
+    }
+
+    // only states that can do at least the same actions can simulate
+    let mut simulators = (0..n).map(|v| {
+        (0..n).filter(|&u| enabled[v].is_subset(&enabled[u])).collect::<BitSet>()
+    }).collect::<Vec<BitSet>>();
+
+    // remove[(a, v)]: states with an a-step, but none into the simulators of v
+    let mut remove: HashMap<(usize, usize), BitSet> = HashMap::new();
+    let mut queue = VecDeque::new();
+    for a in 0..actions {
+        for v in 0..n {
+            let mut reaching = BitSet::new();
+            for u in simulators[v].iter() {
+                reaching.extend(pre[a][u].iter().cloned());
+            }
+            let r = can[a].difference(&reaching).collect::<BitSet>();
+            if !r.is_empty() {
+                remove.insert((a, v), r);
+                queue.push_back((a, v));
+            }
+        }
+    }
+
+    while let Some((a, v)) = queue.pop_front() {
+        let r = match remove.remove(&(a, v)) {
+            Some(r) => r,
+            None => continue
+        };
+        // a state removed here cannot match the a-step of any predecessor into v
+        for &u in &pre[a][v] {
+            for w in r.iter() {
+                if !simulators[u].remove(w) {
+                    continue;
+                }
+                for b in 0..actions {
+                    for &x in &pre[b][w] {
+                        if post[b][x].is_disjoint(&simulators[u]) {
+                            let entry = remove.entry((b, u)).or_insert(BitSet::new());
+                            if entry.is_empty() {
+                                queue.push_back((b, u));
+                            }
+                            entry.insert(x);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    simulators
+}
+
+// Whether state `s` simulates state `u` of the same LTS.
+pub fn state_simulates<L: Clone+Copy>(k: &MixedKripkeStructure<L>, s: usize, u: usize) -> bool {
+    let t = Transitions::new(k);
+    simulation_preorder(&t)[t.index[&u]].contains(t.index[&s])
+}
+
+// Whether the initial state of `a` simulates the initial state of `b`.
+pub fn simulates<L: Clone+Copy>(a: &MixedKripkeStructure<L>, b: &MixedKripkeStructure<L>) -> bool {
+    let (union, s, u) = disjoint_union(a, b);
+    state_simulates(&union, s, u)
+}
+
+#[cfg(test)]
+mod tests {
+    use algorithms::bisimulation::Transitions;
+    use algorithms::simulation::{simulation_preorder, state_simulates, simulates};
+    use utils::random::{Random, random_kripke, lts};
+    use bit_set::BitSet;
+
+    // The greatest simulation by removing pairs until nothing changes.
+    fn naive_preorder(t: &Transitions) -> Vec<BitSet> {
+        let n = t.len();
+        let mut simulators = vec![(0..n).collect::<BitSet>(); n];
+        loop {
+            let mut changed = false;
+            for v in 0..n {
+                for u in 0..n {
+                    if !simulators[v].contains(u) {
+                        continue;
+                    }
+                    let matched = t.outgoing[v].iter().all(|&(a, v2)| {
+                        t.outgoing[u].iter().any(|&(b, u2)| a == b && simulators[v2].contains(u2))
+                    });
+                    if !matched {
+                        simulators[v].remove(u);
+                        changed = true;
+                    }
+                }
+            }
+            if !changed {
+                return simulators;
+            }
+        }
+    }
+
+    // a.(b + c) from 0 and a.b + a.c from 4
+    #[test]
+    fn postponed_choice_simulates_early_choice() {
+        let k = lts(&[(0, "a", 1), (1, "b", 2), (1, "c", 3), (4, "a", 5), (5, "b", 6), (4, "a", 7), (7, "c", 8)]);
+        assert!(state_simulates(&k, 0, 4));
+        assert!(!state_simulates(&k, 4, 0));
+        assert!(state_simulates(&k, 1, 5) && state_simulates(&k, 1, 7));
+        assert!(simulates(&lts(&[(0, "a", 1), (1, "b", 2)]), &lts(&[(0, "a", 1)])));
+        assert!(!simulates(&lts(&[(0, "a", 1)]), &lts(&[(0, "a", 1), (1, "b", 2)])));
+    }
+
+    #[test]
+    fn agrees_with_naive_fixpoint() {
+        let actions = vec!(String::from("a"), String::from("b"), String::from("c"));
+        for seed in 1..101 {
+            let mut rng = Random::new(seed);
+            let states = 1 + rng.below(10);
+            let transitions = rng.below(3 * states);
+            let k = random_kripke(&mut rng, states, transitions, &actions);
+            let t = Transitions::new(&k);
+            assert_eq!(simulation_preorder(&t), naive_preorder(&t), "seed {}", seed);
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use algorithms::parity_game::{ParityGame, Player, Solution};
+use std::collections::VecDeque;
+use bit_set::BitSet;
+
+// The order in which vertices are lifted. Linear sweeps over all vertices
+// until nothing changes, Predecessor only revisits the predecessors of
+// vertices whose measure went up.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LiftingStrategy {
+    Linear,
+    Predecessor
+}
+
+// A progress measure is a vector with a counter per rank; only the counters
+// of odd ranks are used. None is the top element.
+type Measure = Option<Vec<usize>>;
+
+// The measures for one player. Ranks are priorities reversed into a
+// min-parity game in which `player` wins on even ranks, so that Jurdzinski's
+// formulation applies directly.
+struct Measures<'a> {
+    game: &'a ParityGame,
+    player: Player,
+    rank: Vec<usize>,
+    // number of vertices of each odd rank, the bound of its counter
+    bounds: Vec<usize>,
+    rho: Vec<Measure>
+}
+
+impl<'a> Measures<'a> {
+    fn new(game: &'a ParityGame, player: Player) -> Measures<'a> {
+        let max = game.priority.iter().cloned().max().unwrap_or(0);
+        // the smallest number above max with the parity that maps the
+        // priorities won by `player` to even ranks
+        let top = if Player::of_priority(max) == player { max } else { max + 1 };
+        let rank = game.priority.iter().map(|&p| top - p).collect::<Vec<usize>>();
+        let mut bounds = vec![0; top + 1];
+        for &r in &rank {
+            if r % 2 == 1 {
+                bounds[r] += 1;
+            }
+        }
+        Measures {
+            game: game,
+            player: player,
+            rank: rank,
+            bounds: bounds,
+            rho: vec![Some(vec![0; top + 1]); game.len()]
+        }
+    }
+
+    // The least measure that is at least rho(w) up to rank of v, and strictly
+    // larger when that rank is odd.
+    fn prog(&self, v: usize, w: usize) -> Measure {
+        let r = self.rank[v];
+        let mut m = match self.rho[w] {
+            Some(ref m) => m.clone(),
+            None => return None
+        };
+        for i in (r + 1)..m.len() {
+            m[i] = 0;
+        }
+        if r % 2 == 0 {
+            return Some(m);
+        }
+        // increment as a counter with digit i bounded by bounds[i]
+        let mut i = r;
+        loop {
+            if m[i] < self.bounds[i] {
+                m[i] += 1;
+                return Some(m);
+            }
+            m[i] = 0;
+            if i < 2 {
+                return None;
+            }
+            i -= 2;
+        }
+    }
+
+    // The successor that gives the least measure, with that measure.
+    fn best(&self, v: usize) -> Option<(usize, Measure)> {
+        let mut best: Option<(usize, Measure)> = None;
+        for &w in &self.game.successors[v] {
+            let m = self.prog(v, w);
+            let better = match best {
+                Some((_, ref b)) => less(&m, b),
+                None => true
+            };
+            if better {
+                best = Some((w, m));
+            }
+        }
+        best
+    }
+
+    // Lifts v; returns whether its measure increased.
+    fn lift(&mut self, v: usize) -> bool {
+        if self.rho[v].is_none() {
+            return false;
+        }
+        let lifted = if self.game.owner[v] == self.player {
+            match self.best(v) {
+                Some((_, m)) => m,
+                // the player is stuck
+                None => None
+            }
+        } else {
+            let mut worst = self.rho[v].clone();
+            for &w in &self.game.successors[v] {
+                let m = self.prog(v, w);
+                if less(&worst, &m) {
+                    worst = m;
+                }
+            }
+            worst
+        };
+        if less(&self.rho[v], &lifted) {
+            self.rho[v] = lifted;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn solve(&mut self, lifting: LiftingStrategy) {
+        match lifting {
+            LiftingStrategy::Linear => {
+                let mut changed = true;
+                while changed {
+                    changed = false;
+                    for v in 0..self.game.len() {
+                        if self.lift(v) {
+                            changed = true;
+                        }
+                    }
+                }
+            }
+            LiftingStrategy::Predecessor => {
+                let mut queue = (0..self.game.len()).collect::<VecDeque<usize>>();
+                let mut queued = (0..self.game.len()).collect::<BitSet>();
+                while let Some(v) = queue.pop_front() {
+                    queued.remove(v);
+                    if self.lift(v) {
+                        for &u in &self.game.predecessors[v] {
+                            if self.rho[u].is_some() && queued.insert(u) {
+                                queue.push_back(u);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Lexicographic order on measures with None as top.
+fn less(a: &Measure, b: &Measure) -> bool {
+    match (a, b) {
+        (&None, _) => false,
+        (&Some(_), &None) => true,
+        (&Some(ref a), &Some(ref b)) => a < b
+    }
+}
+
+// Solves the game with Jurdzinski's small progress measures. The measures are
+// computed once for each player: a vertex is won by a player iff its measure
+// for that player stays below top, and a winning move is one to the successor
+// with the least measure.
+pub fn solve(game: &ParityGame, lifting: LiftingStrategy) -> Solution {
+    let mut even = Measures::new(game, Player::Even);
+    even.solve(lifting);
+    let mut odd = Measures::new(game, Player::Odd);
+    odd.solve(lifting);
+
+    let mut winner = vec!();
+    let mut strategy = vec!();
+    for v in 0..game.len() {
+        let measures = if even.rho[v].is_some() { &even } else { &odd };
+        winner.push(measures.player);
+        strategy.push(if game.owner[v] == measures.player {
+            measures.best(v).map(|(w, _)| w)
+        } else {
+            None
+        });
+    }
+    Solution { winner: winner, strategy: strategy }
+}
@@ -0,0 +1,98 @@
+use parsers::mucalculus::{MuFormula, occurs_free};
+use parsers::kripke_structure::MixedKripkeStructure;
+use algorithms::emerson_lei;
+use std::collections::{HashMap, VecDeque};
+use bit_set::BitSet;
+
+fn conjuncts<'a>(mu: &'a MuFormula, result: &mut Vec<&'a MuFormula>) {
+    match *mu {
+        MuFormula::And(_, ref f, ref g) => {
+            conjuncts(f, result);
+            conjuncts(g, result);
+        }
+        _ => result.push(mu)
+    }
+}
+
+fn is_variable(mu: &MuFormula, c: &str) -> bool {
+    match *mu {
+        MuFormula::RecursionValue(_, ref x) => x == c,
+        _ => false
+    }
+}
+
+// Recognises the invariance property nu X.(phi && [a1]X && ... && [an]X),
+// which is [(a1|...|an)*]phi, and AG phi as translated from CTL. Returns the
+// conjuncts of phi and the actions a1..an.
+pub fn safety_invariant(mu: &MuFormula) -> Option<(Vec<&MuFormula>, Vec<String>)> {
+    let (c, body) = match *mu {
+        MuFormula::Nu(_, ref c, ref body) => (c, body),
+        _ => return None
+    };
+    let mut parts = vec!();
+    conjuncts(body, &mut parts);
+    let mut invariant = vec!();
+    let mut actions = vec!();
+    for part in parts {
+        match *part {
+            MuFormula::BoxOp(_, ref ac, ref f) if is_variable(f, c) => {
+                if !actions.contains(ac) {
+                    actions.push(ac.clone());
+                }
+            }
+            _ if !occurs_free(c, part) => invariant.push(part),
+            _ => return None
+        }
+    }
+    Some((invariant, actions))
+}
+
+// A shortest path from a state in `from` to a state in `targets` over
+// transitions labelled with `actions`, found by breadth-first search.
+pub fn shortest_path<L: Clone+Copy>(k: &MixedKripkeStructure<L>, from: &BitSet, targets: &BitSet, actions: &[String]) -> Option<Vec<(u32, String, u32)>> {
+    let mut parent: HashMap<usize, (usize, String)> = HashMap::new();
+    let mut visited = from.clone();
+    let mut queue = from.iter().collect::<VecDeque<usize>>();
+    while let Some(s) = queue.pop_front() {
+        if targets.contains(s) {
+            let mut path = vec!();
+            let mut t = s;
+            while let Some(&(p, ref a)) = parent.get(&t) {
+                path.push((p as u32, a.clone(), t as u32));
+                t = p;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        for a in actions {
+            if let Some(successors) = k.relations.get(&(s as u32, a.clone())) {
+                for t in successors.iter() {
+                    if visited.insert(t) {
+                        parent.insert(t, (s, a.clone()));
+                        queue.push_back(t);
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+// For an invariance property, a shortest trace from an initial state to a
+// state violating the invariant. None when the formula is not an invariance
+// property or holds in all initial states.
+pub fn safety_counterexample<L: Clone+Copy>(k: &MixedKripkeStructure<L>, mu: &MuFormula, internal: &[String]) -> Option<Vec<(u32, String, u32)>> {
+    let (invariant, actions) = match safety_invariant(mu) {
+        Some(pattern) => pattern,
+        None => return None
+    };
+    let mut good = k.states.clone();
+    for part in invariant {
+        match emerson_lei::evaluate(k, part.clone(), internal) {
+            Ok(states) => good.intersect_with(&states),
+            Err(_) => return None
+        }
+    }
+    let violating = k.states.difference(&good).collect::<BitSet>();
+    shortest_path(k, &k.init_states, &violating, &actions)
+}
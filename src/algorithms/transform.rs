@@ -0,0 +1,54 @@
+use parsers::kripke_structure::MixedKripkeStructure;
+use algorithms::locks::reachable;
+use std::collections::HashMap;
+use bit_set::BitSet;
+
+// Relabels every transition with `relabel`, dropping those it maps to None.
+// Transitions that end up with the same source and label are merged.
+pub fn relabel<L: Clone+Copy, F: Fn(&String) -> Option<String>>(k: &MixedKripkeStructure<L>, f: F) -> MixedKripkeStructure<L> {
+    let mut relations: HashMap<(u32, String), BitSet> = HashMap::new();
+    for (&(s, ref a), targets) in k.relations.iter() {
+        if let Some(b) = f(a) {
+            relations.entry((s, b)).or_insert(BitSet::new()).union_with(targets);
+        }
+    }
+    MixedKripkeStructure {
+        states: k.states.clone(),
+        init_states: k.init_states.clone(),
+        relations: relations,
+        label: k.label.clone()
+    }
+}
+
+// Renames the given actions to the internal action `tau`.
+pub fn hide<L: Clone+Copy>(k: &MixedKripkeStructure<L>, actions: &[String], tau: &str) -> MixedKripkeStructure<L> {
+    relabel(k, |a| Some(if actions.contains(a) { String::from(tau) } else { a.clone() }))
+}
+
+// Applies the (from, to) renamings; every action is renamed at most once.
+pub fn rename<L: Clone+Copy>(k: &MixedKripkeStructure<L>, renaming: &[(String, String)]) -> MixedKripkeStructure<L> {
+    relabel(k, |a| Some(renaming.iter().find(|&&(ref from, _)| from == a).map_or(a.clone(), |&(_, ref to)| to.clone())))
+}
+
+// Removes the transitions labelled with the given actions.
+pub fn block<L: Clone+Copy>(k: &MixedKripkeStructure<L>, actions: &[String]) -> MixedKripkeStructure<L> {
+    relabel(k, |a| if actions.contains(a) { None } else { Some(a.clone()) })
+}
+
+// Keeps only the transitions labelled with the given or internal actions.
+pub fn allow<L: Clone+Copy>(k: &MixedKripkeStructure<L>, actions: &[String], internal: &[String]) -> MixedKripkeStructure<L> {
+    relabel(k, |a| if actions.contains(a) || internal.contains(a) { Some(a.clone()) } else { None })
+}
+
+// The LTS restricted to the states reachable from the initial states.
+pub fn restrict_to_reachable<L: Clone+Copy>(k: &MixedKripkeStructure<L>) -> MixedKripkeStructure<L> {
+    let states = reachable(k);
+    let relations = k.relations.iter().filter(|&(&(s, _), _)| states.contains(s as usize)).map(|(key, targets)| (key.clone(), targets.clone())).collect();
+    let label = k.label.iter().filter(|&(&s, _)| states.contains(s as usize)).map(|(&s, labels)| (s, labels.clone())).collect();
+    MixedKripkeStructure {
+        states: states,
+        init_states: k.init_states.clone(),
+        relations: relations,
+        label: label
+    }
+}
@@ -0,0 +1,55 @@
+use algorithms::parity_game::{ParityGame, Player, Solution, attractor};
+use bit_set::BitSet;
+
+// Solves the game with Zielonka's recursive algorithm.
+pub fn solve(game: &ParityGame) -> Solution {
+    let mut strategy = vec![None; game.len()];
+    let all = (0..game.len()).collect::<BitSet>();
+    let (even, _) = zielonka(game, &all, &mut strategy);
+    let winner = (0..game.len()).map(|v| if even.contains(v) { Player::Even } else { Player::Odd }).collect::<Vec<Player>>();
+    // only keep the moves of the winner of each vertex
+    for v in 0..game.len() {
+        if game.owner[v] != winner[v] {
+            strategy[v] = None;
+        }
+    }
+    Solution { winner: winner, strategy: strategy }
+}
+
+// Returns the winning regions of Even and Odd in the subgame on `vertices`,
+// writing winning moves for the vertices it decides into `strategy`.
+fn zielonka(game: &ParityGame, vertices: &BitSet, strategy: &mut Vec<Option<usize>>) -> (BitSet, BitSet) {
+    if vertices.is_empty() {
+        return (BitSet::new(), BitSet::new());
+    }
+    let d = vertices.iter().map(|v| game.priority[v]).max().unwrap();
+    let player = Player::of_priority(d);
+    let top = vertices.iter().filter(|&v| game.priority[v] == d).collect::<BitSet>();
+    let a = attractor(game, player, &top, vertices, strategy);
+    let rest = vertices.difference(&a).collect::<BitSet>();
+    let (even, odd) = zielonka(game, &rest, strategy);
+    let lost = if player == Player::Even { odd } else { even };
+
+    if lost.is_empty() {
+        // the player of the highest priority wins everything; from the
+        // vertices with that priority any move inside the subgame will do
+        for v in top.iter() {
+            if game.owner[v] == player {
+                strategy[v] = game.successors[v].iter().cloned().find(|&w| vertices.contains(w));
+            }
+        }
+        let all = vertices.clone();
+        return match player {
+            Player::Even => (all, BitSet::new()),
+            Player::Odd => (BitSet::new(), all)
+        };
+    }
+
+    let b = attractor(game, player.opponent(), &lost, vertices, strategy);
+    let rest = vertices.difference(&b).collect::<BitSet>();
+    let (even, odd) = zielonka(game, &rest, strategy);
+    match player {
+        Player::Even => (even, odd.union(&b).collect::<BitSet>()),
+        Player::Odd => (even.union(&b).collect::<BitSet>(), odd)
+    }
+}
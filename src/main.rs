@@ -9,15 +9,46 @@ extern crate bit_set;
 use std::error::Error;
 use std::fs::File;
 use std::io::prelude::*;
+use std::io::{BufReader, BufWriter};
 use std::path::Path;
 mod parsers;
-use parsers::aldebaran::read_aut_file;
-use parsers::mucalculus::read_mu_formula;
-use parsers::kripke_structure::from_aut_to_kripke;
+use parsers::aldebaran::{read_aut_file, write_aut_file};
+use parsers::mucalculus::{read_mu_formula, pretty, Syntax};
+use parsers::kripke_structure::{MixedKripkeStructure, from_aut_to_kripke};
+use parsers::mucalculus::MuFormula;
+use parsers::ctl::{read_ctl_formula, to_mu_formula};
+use parsers::ltl::read_ltl_formula;
+use parsers::pgsolver::{write_game, read_solution};
 mod algorithms;
 use algorithms::naive;
 use algorithms::emerson_lei;
 use algorithms::depth::{nesting_depth,alternation_depth,dependent_alternation_depth};
+use algorithms::simplify::simplify;
+use algorithms::ltl;
+use algorithms::fairness;
+use algorithms::fairness::Fairness;
+use algorithms::parity_game;
+use algorithms::zielonka;
+use algorithms::spm;
+use algorithms::spm::LiftingStrategy;
+use algorithms::local;
+use algorithms::evidence;
+use algorithms::trace;
+use algorithms::locks;
+use algorithms::scc;
+use algorithms::bisimulation;
+use algorithms::branching;
+use algorithms::equivalence;
+use algorithms::equivalence::Equivalence;
+use algorithms::distinguish;
+use algorithms::refinement;
+use algorithms::refinement::Model;
+use algorithms::simulation;
+use algorithms::transform;
+use algorithms::composition;
+use algorithms::composition::Synchronisation;
+use algorithms::determinise;
+use std::collections::HashMap;
 use std::env;
 mod utils;
 
@@ -31,7 +62,10 @@ extern crate stopwatch;
 use stopwatch::{Stopwatch};
 
 fn main() {    
-    let mut aut = None;
+    let mut kripke = None;
+    // every model that was opened, by name; `kripke` is the one in use
+    let mut models: HashMap<String, MixedKripkeStructure<()>> = HashMap::new();
+    let mut current = String::new();
     let config = Config::builder()
         .history_ignore_space(true)
         .completion_type(CompletionType::List)
@@ -44,12 +78,36 @@ fn main() {
         println!("No previous history.");
     }
     println!("");
-    println!("To open a file: open diner.lts");
+    println!("To open a file: open diner.lts, or open name = diner.lts to choose its name, followed by prune to drop unreachable states");
+    println!("To drop the states of the loaded model that are unreachable from its initial states: prune");
+    println!("To switch between opened models: models, use name");
+    println!("To compare two opened models: compare spec impl strong|branching|divbranching|weak|trace");
+    println!("To compose opened models in parallel: compose name m1 m2 ..., synchronising on shared actions, or compose name m1 m2 ... comm a|b->c,d|e->f");
+    println!("To check whether impl refines spec: refines spec impl trace|failures");
+    println!("To check whether a model or state simulates another: simulates A B");
+    println!("To explain why two states are not strongly bisimilar: distinguish 0 1");
     println!("To exit type: quit");
-    println!("Furthermore, you can enter any µ-calculus formula");
+    println!("To change the algorithm: switch, or engine naive|emerson_lei|zielonka|spm [linear|predecessor]|local");
+    println!("To toggle formula simplification: simplify");
+    println!("To pretty-print a formula: print [mcrl2] <formula>");
+    println!("To cross-check with PGSolver: pgsolver export game.gm <formula>, pgsolver import game.sol <formula>");
+    println!("To explain the verdict in the initial state: evidence <formula>, or evidence export evidence.aut <formula>");
+    println!("To minimise the loaded model: reduce strong|branching|divbranching|trace, where trace gives the least deterministic model");
+    println!("To make the loaded model deterministic, hiding internal actions: determinise");
+    println!("To change the loaded model: hide a,b | rename a->b,c->d | block c | allow a,b, followed by prune to drop unreachable states");
+    println!("To set the internal actions (tau and i by default): internal tau,i");
+    println!("To find deadlocks or cycles of internal actions: deadlocks, livelocks");
+    println!("To show strongly connected components, optionally using only some actions: scc [a,b]");
+    println!("To restrict CTL path quantifiers to fair paths: fair weak|strong a,b, where paths ending in a deadlock count as fair; fair clear to drop them");
+    println!("Furthermore, you can enter any µ-calculus formula, or a CTL or LTL formula prefixed with: ctl or ltl");
+    println!("A violated invariant nu X.(phi && [a]X && [b]X), or ctl AG phi, is shown with a shortest counterexample");
+    println!("Weak modalities <<a>>f and [[a]]f allow any number of internal steps before and after a");
 
     let mut args = false;
-    let mut use_optimized = false;
+    let mut engine = Engine::Naive;
+    let mut use_simplify = false;
+    let mut constraints: Vec<Fairness> = vec!();
+    let mut internal = locks::internal_actions();
     let total_sw = Stopwatch::start_new();
     'outer: loop {
         let readline = if !args {
@@ -70,16 +128,58 @@ fn main() {
                         break 'outer;
                     }
                     if line == "switch" {
-                        use_optimized = !use_optimized;
-                        if use_optimized {
-                            println!("Now using the Emerson Lei algorithm");
+                        engine = match engine {
+                            Engine::Naive => Engine::EmersonLei,
+                            Engine::EmersonLei => Engine::Zielonka,
+                            Engine::Zielonka => Engine::ProgressMeasures(LiftingStrategy::Predecessor),
+                            Engine::ProgressMeasures(_) => Engine::Local,
+                            Engine::Local => Engine::Naive
+                        };
+                        println!("Now using the {}", engine.description());
+                    } else if line.starts_with("engine ") {
+                        match line.replacen("engine ", "", 1).trim() {
+                            "naive" => engine = Engine::Naive,
+                            "emerson_lei" => engine = Engine::EmersonLei,
+                            "zielonka" => engine = Engine::Zielonka,
+                            "spm" | "spm predecessor" => engine = Engine::ProgressMeasures(LiftingStrategy::Predecessor),
+                            "spm linear" => engine = Engine::ProgressMeasures(LiftingStrategy::Linear),
+                            "local" => engine = Engine::Local,
+                            other => println!("Unknown engine {}, choose from: naive, emerson_lei, zielonka, spm [linear|predecessor], local", other)
+                        }
+                        println!("Now using the {}", engine.description());
+                    } else if line == "simplify" {
+                        use_simplify = !use_simplify;
+                        if use_simplify {
+                            println!("Formulas are now simplified before evaluation");
                         } else {
-                            println!("Now using the naive algorithm");
+                            println!("Formulas are now evaluated as entered");
+                        }
+                    } else if line.starts_with("print ") {
+                        let (syntax, formula) = if line.starts_with("print mcrl2 ") {
+                            (Syntax::Mcrl2, line.replacen("print mcrl2 ", "", 1))
+                        } else {
+                            (Syntax::Native, line.replacen("print ", "", 1))
+                        };
+                        match read_mu_formula(formula.replace(" ", "").as_str()) {
+                            Ok(mu) => println!("{}", pretty(&mu, syntax, &internal)),
+                            Err(why) => println!("couldn't parse mu: {}", why.description()),
                         }
                     } else if line.starts_with("open") {
                         let sw = Stopwatch::start_new();
-                        let file_path_string = line.clone().replace("open ", "");
+                        // open <file> [prune] or open <name> = <file> [prune]
+                        let args = String::from(line.replacen("open", "", 1).trim());
+                        let mut rest = args.as_str();
+                        let mut prune = false;
+                        if rest.ends_with(" prune") {
+                            prune = true;
+                            rest = rest[..rest.len() - " prune".len()].trim_right();
+                        }
+                        let (name, file_path_string) = match rest.find('=') {
+                            Some(i) => (Some(String::from(rest[..i].trim())), String::from(rest[i + 1..].trim())),
+                            None => (None, String::from(rest))
+                        };
                         let path = Path::new(file_path_string.as_str());
+                        let name = name.unwrap_or_else(|| path.file_stem().map_or(file_path_string.clone(), |stem| stem.to_string_lossy().into_owned()));
                         let display = path.display();
 
                         match File::open(&path) {
@@ -89,7 +189,22 @@ fn main() {
                                 // match file.read_to_string(&mut s) {
                                     // Err(why) => println!("couldn't read {}: {}", display, why.description()),
                                     // Ok(_) => {
-                                        aut = Some(read_aut_file(file));
+                                        let aut = read_aut_file(file);
+                                        println!("Loading AUT file took {}ms", sw.elapsed_ms());
+                                        let sw = Stopwatch::start_new();
+                                        let model = from_aut_to_kripke(&aut);
+                                        println!("Converting to Kripke took {}ms", sw.elapsed_ms());
+                                        let model = if prune {
+                                            let pruned = transform::restrict_to_reachable(&model);
+                                            println!("Dropped {} of {} states that are unreachable from the initial states", model.states.len() - pruned.states.len(), model.states.len());
+                                            pruned
+                                        } else {
+                                            model
+                                        };
+                                        println!("Opened as {}", name);
+                                        models.insert(name.clone(), model.clone());
+                                        kripke = Some(model);
+                                        current = name;
                                         // match read_aut_file(&s) {
                                             // Ok(result) => { aut = Some(result); },
                                             // Err(why) => println!("syntax error {}: {}", display, why)
@@ -98,40 +213,496 @@ fn main() {
                                 // }
                             }
                         };
-                        println!("Loading AUT file took {}ms", sw.elapsed_ms());
-                    } else {
-                        match aut.clone() {
-                            Some(aut_result) => {
-                                let sw = Stopwatch::start_new();
-                                let mu = read_mu_formula(line.replace(" ", "").as_str());
-                                println!("Reading MU formula took {}ms", sw.elapsed_ms());
-
-                                println!("States: {:?}", mu.clone());
-                                match mu {
-                                    Ok(mu) => {
-                                        let nd = nesting_depth(&mu);
-                                        let ad = alternation_depth(&mu);
-                                        let dad = dependent_alternation_depth(&mu);
-                                        println!("ND: {}, AD: {}, dAD: {}", nd, ad, dad);
-
-                                        let sw = Stopwatch::start_new();
-                                        let kripke = &from_aut_to_kripke(&aut_result);
-                                        println!("Converting to Kripke took {}ms", sw.elapsed_ms());
+                    } else if line.starts_with("fair") {
+                        let args = line.replacen("fair", "", 1);
+                        let args = args.trim();
+                        let actions = |s: &str| s.split(",").map(|a| String::from(a.trim())).filter(|a| a.len() > 0).collect::<Vec<String>>();
+                        if args == "clear" {
+                            constraints.clear();
+                        } else if args.starts_with("weak ") {
+                            constraints.push(Fairness::Weak(actions(&args[5..])));
+                        } else if args.starts_with("strong ") {
+                            constraints.push(Fairness::Strong(actions(&args[7..])));
+                        } else if args.len() > 0 {
+                            println!("Usage: fair weak a,b | fair strong a,b | fair clear");
+                        }
+                        println!("Fairness constraints: {:?}", constraints);
+                    } else if line.starts_with("ctl ") && constraints.len() > 0 {
+                        match kripke {
+                            Some(ref kripke) => {
+                                match read_ctl_formula(line.replacen("ctl ", "", 1).replace(" ", "").as_str()) {
+                                    Ok(ctl) => {
                                         let sw = Stopwatch::start_new();
-                                        let result = if use_optimized {
-                                            emerson_lei::evaluate(kripke, mu).unwrap()
-                                        } else {
-                                            naive::evaluate(kripke, mu).unwrap()
-                                        };
-                                        println!("Executing formula took {}ms", sw.elapsed_ms());
-                                        let n = result.clone().len() as u64;
+                                        let result = fairness::evaluate(kripke, &ctl, &constraints);
+                                        println!("Executing formula under fairness took {}ms", sw.elapsed_ms());
+                                        let n = result.len();
                                         if n < 1000 {
                                             println!("{:?}", result);
                                         }
-                                        println!("Number states from µ-formula: {}, total states: {}", n, aut_result.header.nr_of_states);
-                                        println!("Total ({},{})",aut_result.header.nr_of_states,total_sw.elapsed_ms());
+                                        println!("Number states from CTL formula: {}, total states: {}", n, kripke.states.len());
+                                    },
+                                    Err(why) => println!("couldn't parse ctl: {}", why.description()),
+                                }
+                            },
+                            None => {
+                                println!("No file loaded yet. Open file with: open diner.lts");
+                            }
+                        }
+                    } else if line.starts_with("ltl ") {
+                        match kripke {
+                            Some(ref kripke) => {
+                                match read_ltl_formula(line.replacen("ltl ", "", 1).replace(" ", "").as_str()) {
+                                    Ok(ltl) => {
+                                        let sw = Stopwatch::start_new();
+                                        let result = ltl::check(kripke, &ltl);
+                                        println!("Checking LTL formula took {}ms", sw.elapsed_ms());
+                                        match result {
+                                            None => println!("The formula holds on all traces, where a deadlock repeats {} forever", ltl::DEADLOCK),
+                                            Some(lasso) => {
+                                                println!("Counterexample:");
+                                                for &(s, ref a, t) in &lasso.prefix {
+                                                    println!("  {} -{}-> {}", s, a, t);
+                                                }
+                                                println!("loop:");
+                                                for &(s, ref a, t) in &lasso.cycle {
+                                                    println!("  {} -{}-> {}", s, a, t);
+                                                }
+                                            }
+                                        }
+                                    },
+                                    Err(why) => println!("couldn't parse ltl: {}", why.description()),
+                                }
+                            },
+                            None => {
+                                println!("No file loaded yet. Open file with: open diner.lts");
+                            }
+                        }
+                    } else if line.starts_with("evidence ") {
+                        let args = line.replacen("evidence ", "", 1);
+                        let (file, formula) = if args.starts_with("export ") {
+                            let mut split = args.splitn(3, " ").skip(1);
+                            (split.next(), String::from(split.next().unwrap_or("")))
+                        } else {
+                            (None, args.clone())
+                        };
+                        match kripke {
+                            Some(ref kripke) => {
+                                match read_formula(formula.trim(), kripke).map(|mu| evidence::evidence(kripke, &mu, &internal)) {
+                                    Ok(Ok(evidence)) => {
+                                        match evidence.trace() {
+                                            _ if evidence.transitions.is_empty() => {
+                                                println!("The formula {} trivially in the initial state {}, without taking any transition", if evidence.holds { "holds" } else { "fails" }, evidence.initial);
+                                            },
+                                            Some(trace) => {
+                                                println!("The formula {} in the initial state {}", if evidence.holds { "holds" } else { "does not hold" }, evidence.initial);
+                                                println!("{}:", if evidence.holds { "Witness" } else { "Counterexample" });
+                                                for (s, a, t) in trace {
+                                                    println!("  {} -{}-> {}", s, a, t);
+                                                }
+                                            },
+                                            None => {
+                                                println!("The formula {} in the initial state {}", if evidence.holds { "holds" } else { "does not hold" }, evidence.initial);
+                                                println!("{} with {} transitions:", if evidence.holds { "Witness" } else { "Counterexample" }, evidence.transitions.len());
+                                                for &(s, ref a, t) in &evidence.transitions {
+                                                    println!("  {} -{}-> {}", s, a, t);
+                                                }
+                                            }
+                                        }
+                                        if evidence.choices.len() > 0 {
+                                            println!("Winning strategy:");
+                                            for &(s, ref f, t, ref g) in &evidence.choices {
+                                                println!("  {} in {} => {} in {}", f, s, g, t);
+                                            }
+                                        }
+                                        if let Some(file) = file {
+                                            match File::create(file) {
+                                                Ok(out) => match write_aut_file(&evidence.to_aut(), &mut BufWriter::new(out)) {
+                                                    Ok(_) => println!("Wrote evidence to {}", file),
+                                                    Err(why) => println!("couldn't write {}: {}", file, why.description())
+                                                },
+                                                Err(why) => println!("couldn't create {}: {}", file, why.description())
+                                            }
+                                        }
+                                    },
+                                    Ok(Err(why)) => println!("couldn't build parity game: {:?}", why),
+                                    Err(why) => println!("{}", why)
+                                }
+                            },
+                            None => {
+                                println!("No file loaded yet. Open file with: open diner.lts");
+                            }
+                        }
+                    } else if line == "internal" || line.starts_with("internal ") {
+                        let actions = line.replacen("internal", "", 1).split(",").map(|a| String::from(a.trim())).filter(|a| a.len() > 0).collect::<Vec<String>>();
+                        if actions.len() > 0 {
+                            internal = actions;
+                        }
+                        println!("Internal actions: {}", internal.join(", "));
+                    } else if line.starts_with("reduce") {
+                        let equivalence = line.replacen("reduce", "", 1);
+                        let reduced = match kripke {
+                            Some(ref kripke) => {
+                                let sw = Stopwatch::start_new();
+                                let reduced = match equivalence.trim() {
+                                    "strong" => Some(bisimulation::reduce_strong(kripke)),
+                                    "branching" => Some(branching::reduce_branching(kripke, &internal, false)),
+                                    "divbranching" => Some(branching::reduce_branching(kripke, &internal, true)),
+                                    "trace" => Some(determinise::reduce_trace(kripke, &internal)),
+                                    _ => {
+                                        println!("Usage: reduce strong|branching|divbranching|trace");
+                                        None
+                                    }
+                                };
+                                if let Some(ref reduced) = reduced {
+                                    println!("Reducing took {}ms", sw.elapsed_ms());
+                                    println!("States: {} -> {}, transitions: {} -> {}", kripke.states.len(), reduced.states.len(),
+                                        kripke.relations.values().map(|t| t.len()).sum::<usize>(), reduced.relations.values().map(|t| t.len()).sum::<usize>());
+                                }
+                                reduced
+                            },
+                            None => {
+                                println!("No file loaded yet. Open file with: open diner.lts");
+                                None
+                            }
+                        };
+                        if let Some(reduced) = reduced {
+                            models.insert(current.clone(), reduced.clone());
+                            kripke = Some(reduced);
+                        }
+                    } else if line == "determinise" {
+                        let determinised = match kripke {
+                            Some(ref kripke) => {
+                                let sw = Stopwatch::start_new();
+                                let determinised = determinise::determinise(kripke, &internal);
+                                println!("Determinising took {}ms", sw.elapsed_ms());
+                                println!("States: {} -> {}, transitions: {} -> {}", kripke.states.len(), determinised.states.len(),
+                                    kripke.relations.values().map(|t| t.len()).sum::<usize>(), determinised.relations.values().map(|t| t.len()).sum::<usize>());
+                                Some(determinised)
+                            },
+                            None => {
+                                println!("No file loaded yet. Open file with: open diner.lts");
+                                None
+                            }
+                        };
+                        if let Some(determinised) = determinised {
+                            models.insert(current.clone(), determinised.clone());
+                            kripke = Some(determinised);
+                        }
+                    } else if line == "prune" {
+                        let pruned = match kripke {
+                            Some(ref kripke) => {
+                                let pruned = transform::restrict_to_reachable(kripke);
+                                println!("Dropped {} of {} states that are unreachable from the initial states", kripke.states.len() - pruned.states.len(), kripke.states.len());
+                                Some(pruned)
+                            },
+                            None => {
+                                println!("No file loaded yet. Open file with: open diner.lts");
+                                None
+                            }
+                        };
+                        if let Some(pruned) = pruned {
+                            models.insert(current.clone(), pruned.clone());
+                            kripke = Some(pruned);
+                        }
+                    } else if line.starts_with("hide ") || line.starts_with("rename ") || line.starts_with("block ") || line.starts_with("allow ") {
+                        let mut words = line.split_whitespace().collect::<Vec<&str>>();
+                        let prune = words.last() == Some(&"prune");
+                        if prune {
+                            words.pop();
+                        }
+                        let actions = words[1..].join("").split(",").map(String::from).filter(|a| a.len() > 0).collect::<Vec<String>>();
+                        let transformed = match kripke {
+                            Some(ref kripke) => {
+                                let transformed = match words[0] {
+                                    "hide" => Some(transform::hide(kripke, &actions, internal.first().map_or("tau", |tau| tau.as_str()))),
+                                    "block" => Some(transform::block(kripke, &actions)),
+                                    "allow" => Some(transform::allow(kripke, &actions, &internal)),
+                                    _ => {
+                                        let renaming = actions.iter().filter_map(|r| {
+                                            let parts = r.split("->").collect::<Vec<&str>>();
+                                            if parts.len() == 2 { Some((String::from(parts[0]), String::from(parts[1]))) } else { None }
+                                        }).collect::<Vec<(String, String)>>();
+                                        if renaming.len() == actions.len() {
+                                            Some(transform::rename(kripke, &renaming))
+                                        } else {
+                                            println!("Usage: rename a->b,c->d");
+                                            None
+                                        }
+                                    }
+                                };
+                                transformed.map(|transformed| {
+                                    let transformed = if prune { transform::restrict_to_reachable(&transformed) } else { transformed };
+                                    println!("States: {} -> {}, transitions: {} -> {}", kripke.states.len(), transformed.states.len(),
+                                        kripke.relations.values().map(|t| t.len()).sum::<usize>(), transformed.relations.values().map(|t| t.len()).sum::<usize>());
+                                    println!("Actions: {}", transformed.actions().join(", "));
+                                    transformed
+                                })
+                            },
+                            None => {
+                                println!("No file loaded yet. Open file with: open diner.lts");
+                                None
+                            }
+                        };
+                        if let Some(transformed) = transformed {
+                            models.insert(current.clone(), transformed.clone());
+                            kripke = Some(transformed);
+                        }
+                    } else if line == "models" {
+                        let mut names = models.keys().cloned().collect::<Vec<String>>();
+                        names.sort();
+                        for name in names {
+                            println!("{}{}: {} states", if name == current { "* " } else { "  " }, name, models[&name].states.len());
+                        }
+                    } else if line.starts_with("use ") {
+                        let name = line.replacen("use ", "", 1).trim().to_owned();
+                        match models.get(&name) {
+                            Some(model) => {
+                                kripke = Some(model.clone());
+                                println!("Now using {}", name);
+                                current = name.clone();
+                            },
+                            None => println!("No model named {}", name)
+                        }
+                    } else if line.starts_with("distinguish ") {
+                        let states = line.replacen("distinguish ", "", 1).split_whitespace().filter_map(|s| s.parse::<usize>().ok()).collect::<Vec<usize>>();
+                        match kripke {
+                            Some(ref kripke) if states.len() == 2 && states.iter().all(|&s| kripke.states.contains(s)) => {
+                                let t = bisimulation::Transitions::new(kripke);
+                                let history = distinguish::refinement_history(&t);
+                                match distinguish::distinguishing_formula(&t, &history, states[0], states[1]) {
+                                    Some(formula) => println!("True in {} and false in {}: {}", states[0], states[1], formula.to_string()),
+                                    None => println!("{} and {} are strongly bisimilar", states[0], states[1])
+                                }
+                            },
+                            Some(_) => println!("Usage: distinguish s t, for two states of the loaded model"),
+                            None => {
+                                println!("No file loaded yet. Open file with: open diner.lts");
+                            }
+                        }
+                    } else if line.starts_with("compose ") {
+                        let args = line.split_whitespace().skip(1).collect::<Vec<&str>>();
+                        let (names, rules) = match args.iter().position(|&word| word == "comm") {
+                            Some(i) => (&args[..i], Some(args[i + 1..].join(""))),
+                            None => (&args[..], None)
+                        };
+                        let names = names.iter().map(|&name| String::from(name)).collect::<Vec<String>>();
+                        let synchronisation = match rules {
+                            None => Some(Synchronisation::SharedAlphabet),
+                            Some(rules) => {
+                                let parsed = rules.split(",").map(|rule| {
+                                    let sides = rule.split("->").collect::<Vec<&str>>();
+                                    if sides.len() == 2 && sides[0].contains("|") {
+                                        Some((sides[0].split("|").map(String::from).collect::<Vec<String>>(), String::from(sides[1])))
+                                    } else {
+                                        None
+                                    }
+                                }).collect::<Option<Vec<(Vec<String>, String)>>>();
+                                parsed.map(Synchronisation::Communication)
+                            }
+                        };
+                        let components = names.iter().skip(1).filter_map(|name| models.get(name)).collect::<Vec<&MixedKripkeStructure<()>>>();
+                        let composed = match synchronisation {
+                            Some(ref synchronisation) if names.len() > 2 && components.len() == names.len() - 1 => {
+                                let sw = Stopwatch::start_new();
+                                let composed = composition::compose(&components, synchronisation, &internal);
+                                println!("Composing took {}ms", sw.elapsed_ms());
+                                println!("Composition {} has {} reachable states and {} transitions", names[0], composed.states.len(), composed.relations.values().map(|t| t.len()).sum::<usize>());
+                                Some(composed)
+                            },
+                            _ => {
+                                println!("Usage: compose name m1 m2 ... [comm a|b->c,...], for opened models m1, m2, ...");
+                                None
+                            }
+                        };
+                        if let Some(composed) = composed {
+                            models.insert(names[0].clone(), composed.clone());
+                            kripke = Some(composed);
+                            current = names[0].clone();
+                        }
+                    } else if line.starts_with("refines ") {
+                        let args = line.replacen("refines ", "", 1).split_whitespace().map(String::from).collect::<Vec<String>>();
+                        let model = match args.get(2).map(|m| m.as_str()) {
+                            Some("trace") => Some(Model::Traces),
+                            Some("failures") => Some(Model::Failures),
+                            _ => None
+                        };
+                        match (args.get(0).and_then(|name| models.get(name)), args.get(1).and_then(|name| models.get(name)), model) {
+                            (Some(spec), Some(implementation), Some(model)) => {
+                                let sw = Stopwatch::start_new();
+                                let result = refinement::refines(spec, implementation, model, &internal);
+                                println!("Checking refinement took {}ms", sw.elapsed_ms());
+                                match result {
+                                    None => println!("{} refines {}", args[1], args[0]),
+                                    Some(counterexample) => {
+                                        println!("{} does not refine {}", args[1], args[0]);
+                                        println!("Trace: {}", counterexample.trace.join("."));
+                                        if let Some(refusal) = counterexample.refusal {
+                                            println!("after which it refuses: {{{}}}", refusal.join(", "));
+                                        }
+                                    }
+                                }
+                            },
+                            (None, _, _) | (_, None, _) => println!("Open both models first, see: models"),
+                            _ => println!("Usage: refines spec impl trace|failures")
+                        }
+                    } else if line.starts_with("simulates ") {
+                        let args = line.replacen("simulates ", "", 1).split_whitespace().map(String::from).collect::<Vec<String>>();
+                        let states = args.iter().filter_map(|s| s.parse::<usize>().ok()).collect::<Vec<usize>>();
+                        let sw = Stopwatch::start_new();
+                        let result = match (args.get(0).and_then(|name| models.get(name)), args.get(1).and_then(|name| models.get(name))) {
+                            (Some(a), Some(b)) => Some(simulation::simulates(a, b)),
+                            _ => match kripke {
+                                Some(ref kripke) if args.len() == 2 && states.len() == 2 && states.iter().all(|&s| kripke.states.contains(s)) => {
+                                    Some(simulation::state_simulates(kripke, states[0], states[1]))
+                                },
+                                _ => None
+                            }
+                        };
+                        match result {
+                            Some(result) => {
+                                println!("Computing the simulation preorder took {}ms", sw.elapsed_ms());
+                                println!("{} {} {}", args[0], if result { "simulates" } else { "does not simulate" }, args[1]);
+                            },
+                            None => println!("Usage: simulates A B, for two opened models or two states of the loaded model")
+                        }
+                    } else if line.starts_with("compare ") {
+                        let args = line.replacen("compare ", "", 1).split_whitespace().map(String::from).collect::<Vec<String>>();
+                        let equivalence = match args.get(2).map(|e| e.as_str()) {
+                            Some("strong") => Some(Equivalence::Strong),
+                            Some("branching") => Some(Equivalence::Branching),
+                            Some("divbranching") => Some(Equivalence::DivergenceBranching),
+                            Some("weak") => Some(Equivalence::Weak),
+                            Some("trace") => Some(Equivalence::Trace),
+                            _ => None
+                        };
+                        match (args.get(0).and_then(|name| models.get(name)), args.get(1).and_then(|name| models.get(name)), equivalence) {
+                            (Some(spec), Some(implementation), Some(equivalence)) => {
+                                let sw = Stopwatch::start_new();
+                                let comparison = equivalence::compare(spec, implementation, equivalence, &internal);
+                                println!("Comparing took {}ms", sw.elapsed_ms());
+                                if comparison.equivalent {
+                                    println!("{} and {} are equivalent", args[0], args[1]);
+                                } else {
+                                    println!("{} and {} are not equivalent", args[0], args[1]);
+                                    if let Some(trace) = comparison.trace {
+                                        println!("Distinguishing trace: {}", trace.join("."));
+                                    }
+                                    if let Some(formula) = comparison.formula {
+                                        println!("Distinguishing formula, true for {} and false for {}: {}", args[0], args[1], formula.to_string());
+                                    }
+                                }
+                            },
+                            (None, _, _) | (_, None, _) => println!("Open both models first, see: models"),
+                            _ => println!("Usage: compare spec impl strong|branching|divbranching|weak|trace")
+                        }
+                    } else if line == "deadlocks" {
+                        match kripke {
+                            Some(ref kripke) => {
+                                match locks::deadlocks(kripke) {
+                                    None => println!("No reachable deadlocks"),
+                                    Some(deadlock) => {
+                                        if deadlock.states.len() < 1000 {
+                                            println!("{:?}", deadlock.states);
+                                        }
+                                        println!("Number of reachable deadlocks: {}", deadlock.states.len());
+                                        println!("Shortest trace to a deadlock ({} steps):", deadlock.trace.len());
+                                        print_transitions(&deadlock.trace);
+                                    }
+                                }
+                            },
+                            None => {
+                                println!("No file loaded yet. Open file with: open diner.lts");
+                            }
+                        }
+                    } else if line == "scc" || line.starts_with("scc ") {
+                        let actions = line.replacen("scc", "", 1).split(",").map(|a| String::from(a.trim())).filter(|a| a.len() > 0).collect::<Vec<String>>();
+                        match kripke {
+                            Some(ref kripke) => {
+                                let sw = Stopwatch::start_new();
+                                let decomposition = scc::decompose(kripke, &kripke.states, if actions.is_empty() { None } else { Some(&actions[..]) });
+                                println!("Decomposition took {}ms", sw.elapsed_ms());
+                                let sizes = decomposition.components.iter().map(|c| c.len()).collect::<Vec<usize>>();
+                                println!("Components: {}, with a cycle: {}, bottom: {}", sizes.len(), decomposition.nontrivial.iter().filter(|&&b| b).count(), decomposition.bottom().len());
+                                println!("Largest component: {} states, edges between components: {}", sizes.iter().cloned().max().unwrap_or(0), decomposition.condensation.iter().map(|c| c.len()).sum::<usize>());
+                                let init = kripke.init_states.iter().next().and_then(|s| decomposition.component_of.get(&s).cloned());
+                                if let Some(c) = init {
+                                    println!("The initial state is in a component of {} states", sizes[c]);
+                                }
+                            },
+                            None => {
+                                println!("No file loaded yet. Open file with: open diner.lts");
+                            }
+                        }
+                    } else if line == "livelocks" {
+                        match kripke {
+                            Some(ref kripke) => {
+                                let livelocks = locks::livelocks(kripke, &internal);
+                                println!("Number of reachable livelocks: {}", livelocks.len());
+                                for livelock in livelocks.iter().take(10) {
+                                    println!("Livelock of {} states, reached from initial state {} by:", livelock.states.len(), livelock.initial);
+                                    print_transitions(&livelock.trace);
+                                    println!("loop:");
+                                    print_transitions(&livelock.cycle);
+                                }
+                            },
+                            None => {
+                                println!("No file loaded yet. Open file with: open diner.lts");
+                            }
+                        }
+                    } else if line.starts_with("pgsolver ") {
+                        let args = line.replacen("pgsolver ", "", 1);
+                        let mut split = args.trim().splitn(3, " ");
+                        let (command, file, formula) = (split.next().unwrap_or(""), split.next().unwrap_or(""), split.next().unwrap_or(""));
+                        match kripke {
+                            Some(ref kripke) => {
+                                match read_formula(formula, kripke).map(|mu| parity_game::build(kripke, &mu, &internal)) {
+                                    Ok(Ok(game)) => {
+                                        if command == "export" {
+                                            match File::create(file) {
+                                                Ok(out) => match write_game(&game, &mut BufWriter::new(out)) {
+                                                    Ok(_) => println!("Wrote parity game with {} vertices to {}", game.len(), file),
+                                                    Err(why) => println!("couldn't write {}: {}", file, why.description())
+                                                },
+                                                Err(why) => println!("couldn't create {}: {}", file, why.description())
+                                            }
+                                        } else if command == "import" {
+                                            match File::open(file) {
+                                                Ok(input) => match read_solution(&game, BufReader::new(input)) {
+                                                    Ok(solution) => {
+                                                        let result = game.satisfying_states(&solution);
+                                                        if result.len() < 1000 {
+                                                            println!("{:?}", result);
+                                                        }
+                                                        println!("Number states from solution: {}, total states: {}", result.len(), kripke.states.len());
+                                                        let init = kripke.init_states.iter().all(|s| result.contains(s as usize));
+                                                        println!("The formula {} in the initial state", if init { "holds" } else { "does not hold" });
+                                                    },
+                                                    Err(why) => println!("couldn't read solution {}: {:?}", file, why)
+                                                },
+                                                Err(why) => println!("couldn't open {}: {}", file, why.description())
+                                            }
+                                        } else {
+                                            println!("Usage: pgsolver export game.gm <formula> | pgsolver import game.sol <formula>");
+                                        }
                                     },
-                                    Err(why) => println!("couldn't parse mu: {}", why.description()),
+                                    Ok(Err(why)) => println!("couldn't build parity game: {:?}", why),
+                                    Err(why) => println!("{}", why)
+                                }
+                            },
+                            None => {
+                                println!("No file loaded yet. Open file with: open diner.lts");
+                            }
+                        }
+                    } else {
+                        match kripke {
+                            Some(ref kripke) => {
+                                let sw = Stopwatch::start_new();
+                                let mu = read_formula(line, kripke);
+                                println!("Reading formula took {}ms", sw.elapsed_ms());
+
+                                match mu {
+                                    Ok(mu) => check_formula(kripke, mu, engine, use_simplify, &internal, &total_sw),
+                                    Err(why) => println!("{}", why),
                                 }
                             },
                             None => { 
@@ -157,3 +728,111 @@ fn main() {
     }
     rl.save_history("history.txt").unwrap();
 }
+
+// Reads a mu-calculus formula, or a CTL formula prefixed with `ctl `.
+fn read_formula(line: &str, kripke: &MixedKripkeStructure<()>) -> Result<MuFormula, String> {
+    if line.starts_with("ctl ") {
+        read_ctl_formula(line.replacen("ctl ", "", 1).replace(" ", "").as_str())
+            .map(|ctl| to_mu_formula(&ctl, &kripke.actions()))
+            .map_err(|why| format!("couldn't parse ctl: {}", why.description()))
+    } else {
+        read_mu_formula(line.replace(" ", "").as_str())
+            .map_err(|why| format!("couldn't parse mu: {}", why.description()))
+    }
+}
+
+fn print_transitions(transitions: &[(u32, String, u32)]) {
+    for &(s, ref a, t) in transitions {
+        println!("  {} -{}-> {}", s, a, t);
+    }
+}
+
+// Prints a shortest trace to a violating state when the formula is an
+// invariance property nu X.(phi && [a1]X && ... && [an]X), as AG phi is
+// translated from CTL.
+fn print_safety_counterexample(kripke: &MixedKripkeStructure<()>, mu: &MuFormula, internal: &[String]) {
+    if let Some(path) = trace::safety_counterexample(kripke, mu, internal) {
+        println!("Shortest counterexample ({} steps):", path.len());
+        let labels = path.iter().map(|&(_, ref a, _)| a.clone()).collect::<Vec<String>>();
+        print_transitions(&path);
+        println!("Trace: {}", labels.join("."));
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Engine { Naive, EmersonLei, Zielonka, ProgressMeasures(LiftingStrategy), Local }
+
+impl Engine {
+    fn description(&self) -> &'static str {
+        match *self {
+            Engine::Naive => "naive algorithm",
+            Engine::EmersonLei => "Emerson Lei algorithm",
+            Engine::Zielonka => "parity game solved with Zielonka's algorithm",
+            Engine::ProgressMeasures(LiftingStrategy::Linear) => "parity game solved with small progress measures, lifting linearly",
+            Engine::ProgressMeasures(LiftingStrategy::Predecessor) => "parity game solved with small progress measures, lifting predecessors",
+            Engine::Local => "local algorithm, which only decides the initial state"
+        }
+    }
+}
+
+fn check_formula(kripke: &MixedKripkeStructure<()>, mu: MuFormula, engine: Engine, use_simplify: bool, internal: &[String], total_sw: &Stopwatch) {
+    println!("Formula: {}", mu.to_string());
+    let mu = if use_simplify {
+        let simplified = simplify(&mu);
+        println!("Simplified: {}", simplified.to_string());
+        simplified
+    } else {
+        mu
+    };
+    let nd = nesting_depth(&mu);
+    let ad = alternation_depth(&mu);
+    let dad = dependent_alternation_depth(&mu);
+    println!("ND: {}, AD: {}, dAD: {}", nd, ad, dad);
+
+    let sw = Stopwatch::start_new();
+    if engine == Engine::Local {
+        match local::check(kripke, &mu, internal) {
+            Ok(result) => {
+                println!("Executing formula took {}ms", sw.elapsed_ms());
+                println!("Explored {} vertices{}", result.explored, if result.complete { ", which is the whole game" } else { "" });
+                println!("The formula {} in the initial state", if result.holds { "holds" } else { "does not hold" });
+                if !result.holds {
+                    print_safety_counterexample(kripke, &mu, internal);
+                }
+            },
+            Err(why) => println!("couldn't build parity game: {:?}", why)
+        }
+        println!("Total ({},{})", kripke.states.len(), total_sw.elapsed_ms());
+        return;
+    }
+    let result = match engine {
+        Engine::Naive => naive::evaluate(kripke, mu.clone(), internal).unwrap(),
+        Engine::EmersonLei => emerson_lei::evaluate(kripke, mu.clone(), internal).unwrap(),
+        Engine::Local => unreachable!("local checking returns above, as it only decides the initial states"),
+        Engine::Zielonka | Engine::ProgressMeasures(_) => {
+            let game = match parity_game::build(kripke, &mu, internal) {
+                Ok(game) => game,
+                Err(why) => {
+                    println!("couldn't build parity game: {:?}", why);
+                    return;
+                }
+            };
+            println!("Parity game with {} vertices built in {}ms", game.len(), sw.elapsed_ms());
+            let solution = match engine {
+                Engine::ProgressMeasures(lifting) => spm::solve(&game, lifting),
+                _ => zielonka::solve(&game)
+            };
+            game.satisfying_states(&solution)
+        }
+    };
+    println!("Executing formula took {}ms", sw.elapsed_ms());
+    let n = result.clone().len() as u64;
+    if n < 1000 {
+        println!("{:?}", result);
+    }
+    println!("Number states from µ-formula: {}, total states: {}", n, kripke.states.len());
+    if !kripke.init_states.is_subset(&result) {
+        print_safety_counterexample(kripke, &mu, internal);
+    }
+    println!("Total ({},{})", kripke.states.len(), total_sw.elapsed_ms());
+}
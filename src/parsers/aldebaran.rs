@@ -5,6 +5,8 @@ use regex::Regex;
 
 use bufstream::BufStream;
 use std::io::BufRead;
+use std::io::Write;
+use std::io;
 use std::fs::File;
 
 use stopwatch::{Stopwatch};
@@ -86,6 +88,14 @@ pub fn read_aut_file(file_stream: File) -> AutFile {
     return AutFile { header: header.unwrap(), edges: edges };
 }
 
+pub fn write_aut_file<W: Write>(aut: &AutFile, out: &mut W) -> io::Result<()> {
+    try!(writeln!(out, "des ({},{},{})", aut.header.first_state, aut.header.nr_of_transitions, aut.header.nr_of_states));
+    for edge in &aut.edges {
+        try!(writeln!(out, "({},\"{}\",{})", edge.start_state, edge.label, edge.end_state));
+    }
+    Ok(())
+}
+
 
 // pub fn read_aut_file(s: &str) -> IResult<&[u8], AutFile> {
 //     return parse_aut(b"fsdf");
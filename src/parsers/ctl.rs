@@ -0,0 +1,280 @@
+use std::result;
+use parsers::mucalculus::{MuFormula, renumber};
+
+// A CTL formula over the actions of an LTS. Path quantifiers may be
+// restricted to a set of actions, e.g. `EF{send,receive} <ready>true`;
+// without a restriction all actions of the model are used.
+// Paths are maximal, so a path may end in a deadlock state.
+#[derive(Clone, Debug)]
+pub enum CtlFormula {
+    Bool(bool),
+    Not(Box<CtlFormula>),
+    And(Box<CtlFormula>, Box<CtlFormula>),
+    Or(Box<CtlFormula>, Box<CtlFormula>),
+    DiamondOp(String, Box<CtlFormula>),
+    BoxOp(String, Box<CtlFormula>),
+    EX(Option<Vec<String>>, Box<CtlFormula>),
+    AX(Option<Vec<String>>, Box<CtlFormula>),
+    EF(Option<Vec<String>>, Box<CtlFormula>),
+    AF(Option<Vec<String>>, Box<CtlFormula>),
+    EG(Option<Vec<String>>, Box<CtlFormula>),
+    AG(Option<Vec<String>>, Box<CtlFormula>),
+    EU(Option<Vec<String>>, Box<CtlFormula>, Box<CtlFormula>),
+    AU(Option<Vec<String>>, Box<CtlFormula>, Box<CtlFormula>),
+}
+
+peg! ctl_grammar(r#"
+use parsers::ctl::CtlFormula;
+
+__ = [ \t\r\n]*;
+
+label -> String = a:$([a-z_0-9]+) { a.to_owned() };
+restriction -> Vec<String> = "{" __ a:label ** (__ "," __) __ "}" { a };
+
+false -> CtlFormula = "false" { CtlFormula::Bool(false) };
+true -> CtlFormula = "true" { CtlFormula::Bool(true) };
+brackets -> CtlFormula = "(" f:formula ")" { f };
+
+not -> CtlFormula = "!" __ f:unary { CtlFormula::Not(box f) };
+diamond -> CtlFormula = "<" a:label ">" __ f:unary { CtlFormula::DiamondOp(a, box f) };
+box -> CtlFormula = "[" a:label "]" __ f:unary { CtlFormula::BoxOp(a, box f) };
+
+ex -> CtlFormula = "EX" __ r:restriction? __ f:unary { CtlFormula::EX(r, box f) };
+ax -> CtlFormula = "AX" __ r:restriction? __ f:unary { CtlFormula::AX(r, box f) };
+ef -> CtlFormula = "EF" __ r:restriction? __ f:unary { CtlFormula::EF(r, box f) };
+af -> CtlFormula = "AF" __ r:restriction? __ f:unary { CtlFormula::AF(r, box f) };
+eg -> CtlFormula = "EG" __ r:restriction? __ f:unary { CtlFormula::EG(r, box f) };
+ag -> CtlFormula = "AG" __ r:restriction? __ f:unary { CtlFormula::AG(r, box f) };
+eu -> CtlFormula = "E" __ r:restriction? __ "[" f:formula "U" g:formula "]" { CtlFormula::EU(r, box f, box g) };
+au -> CtlFormula = "A" __ r:restriction? __ "[" f:formula "U" g:formula "]" { CtlFormula::AU(r, box f, box g) };
+
+unary -> CtlFormula = not / diamond / box / ex / ax / ef / af / eg / ag / eu / au / false / true / brackets;
+
+conjunction -> CtlFormula = f:unary rest:(__ "&&" __ g:unary { g })* {
+    rest.into_iter().fold(f, |acc, g| CtlFormula::And(box acc, box g))
+};
+disjunction -> CtlFormula = f:conjunction rest:(__ "||" __ g:conjunction { g })* {
+    rest.into_iter().fold(f, |acc, g| CtlFormula::Or(box acc, box g))
+};
+
+pub formula -> CtlFormula = __ f:disjunction __ { f };
+"#);
+
+pub fn read_ctl_formula(s: &str) -> result::Result<CtlFormula, ctl_grammar::ParseError> {
+    return ctl_grammar::formula(s);
+}
+
+// Translates a CTL formula into an equivalent µ-calculus formula. The
+// unrestricted path quantifiers range over `alphabet`, which is normally the
+// set of actions of the model the formula is checked on. Nodes are built
+// with position 0 and numbered once the translation is complete.
+pub fn to_mu_formula(ctl: &CtlFormula, alphabet: &[String]) -> MuFormula {
+    let mut translation = Translation { variables: 0, alphabet: alphabet.to_vec() };
+    renumber(&translation.translate(ctl))
+}
+
+struct Translation {
+    variables: usize,
+    alphabet: Vec<String>
+}
+
+impl Translation {
+    fn variable(&mut self) -> String {
+        self.variables += 1;
+        format!("X{}", self.variables)
+    }
+
+    fn actions(&self, restriction: &Option<Vec<String>>) -> Vec<String> {
+        match *restriction {
+            Some(ref actions) => actions.clone(),
+            None => self.alphabet.clone()
+        }
+    }
+
+    fn and(&self, f: MuFormula, g: MuFormula) -> MuFormula {
+        MuFormula::And(0, box f, box g)
+    }
+
+    fn or(&self, f: MuFormula, g: MuFormula) -> MuFormula {
+        MuFormula::Or(0, box f, box g)
+    }
+
+    fn var(&self, c: &String) -> MuFormula {
+        MuFormula::RecursionValue(0, c.clone())
+    }
+
+    // <a1>f || ... || <an>f, or false for no actions
+    fn exists_next(&self, actions: &[String], f: &MuFormula) -> MuFormula {
+        let mut result = None;
+        for ac in actions {
+            let diamond = MuFormula::DiamondOp(0, ac.clone(), box f.clone());
+            result = Some(match result {
+                Some(r) => self.or(r, diamond),
+                None => diamond
+            });
+        }
+        result.unwrap_or(MuFormula::Bool(0, false))
+    }
+
+    // [a1]f && ... && [an]f, or true for no actions
+    fn all_next(&self, actions: &[String], f: &MuFormula) -> MuFormula {
+        let mut result = None;
+        for ac in actions {
+            let boxed = MuFormula::BoxOp(0, ac.clone(), box f.clone());
+            result = Some(match result {
+                Some(r) => self.and(r, boxed),
+                None => boxed
+            });
+        }
+        result.unwrap_or(MuFormula::Bool(0, true))
+    }
+
+    fn translate(&mut self, ctl: &CtlFormula) -> MuFormula {
+        match *ctl {
+            CtlFormula::Bool(b) => MuFormula::Bool(0, b),
+            CtlFormula::Not(ref f) => {
+                let f = self.translate(f);
+                MuFormula::Not(0, box f)
+            }
+            CtlFormula::And(ref f, ref g) => {
+                let f = self.translate(f);
+                let g = self.translate(g);
+                self.and(f, g)
+            }
+            CtlFormula::Or(ref f, ref g) => {
+                let f = self.translate(f);
+                let g = self.translate(g);
+                self.or(f, g)
+            }
+            CtlFormula::DiamondOp(ref ac, ref f) => {
+                let f = self.translate(f);
+                MuFormula::DiamondOp(0, ac.clone(), box f)
+            }
+            CtlFormula::BoxOp(ref ac, ref f) => {
+                let f = self.translate(f);
+                MuFormula::BoxOp(0, ac.clone(), box f)
+            }
+            CtlFormula::EX(ref r, ref f) => {
+                let actions = self.actions(r);
+                let f = self.translate(f);
+                self.exists_next(&actions, &f)
+            }
+            CtlFormula::AX(ref r, ref f) => {
+                let actions = self.actions(r);
+                let f = self.translate(f);
+                self.all_next(&actions, &f)
+            }
+            // EF f = mu X.(f || EX X)
+            CtlFormula::EF(ref r, ref f) => {
+                let actions = self.actions(r);
+                let f = self.translate(f);
+                let c = self.variable();
+                let x = self.var(&c);
+                let step = self.exists_next(&actions, &x);
+                let body = self.or(f, step);
+                MuFormula::Mu(0, c, box body)
+            }
+            // AG f = nu X.(f && AX X)
+            CtlFormula::AG(ref r, ref f) => {
+                let actions = self.actions(r);
+                let f = self.translate(f);
+                let c = self.variable();
+                let x = self.var(&c);
+                let step = self.all_next(&actions, &x);
+                let body = self.and(f, step);
+                MuFormula::Nu(0, c, box body)
+            }
+            // AF f = mu X.(f || (AX X && EX true))
+            CtlFormula::AF(ref r, ref f) => {
+                let actions = self.actions(r);
+                let f = self.translate(f);
+                let c = self.variable();
+                let x = self.var(&c);
+                let step = self.all_next(&actions, &x);
+                let t = MuFormula::Bool(0, true);
+                let enabled = self.exists_next(&actions, &t);
+                let progress = self.and(step, enabled);
+                let body = self.or(f, progress);
+                MuFormula::Mu(0, c, box body)
+            }
+            // EG f = nu X.(f && (EX X || AX false))
+            CtlFormula::EG(ref r, ref f) => {
+                let actions = self.actions(r);
+                let f = self.translate(f);
+                let c = self.variable();
+                let x = self.var(&c);
+                let step = self.exists_next(&actions, &x);
+                let fl = MuFormula::Bool(0, false);
+                let deadlock = self.all_next(&actions, &fl);
+                let continued = self.or(step, deadlock);
+                let body = self.and(f, continued);
+                MuFormula::Nu(0, c, box body)
+            }
+            // E[f U g] = mu X.(g || (f && EX X))
+            CtlFormula::EU(ref r, ref f, ref g) => {
+                let actions = self.actions(r);
+                let f = self.translate(f);
+                let g = self.translate(g);
+                let c = self.variable();
+                let x = self.var(&c);
+                let step = self.exists_next(&actions, &x);
+                let hold = self.and(f, step);
+                let body = self.or(g, hold);
+                MuFormula::Mu(0, c, box body)
+            }
+            // A[f U g] = mu X.(g || (f && AX X && EX true))
+            CtlFormula::AU(ref r, ref f, ref g) => {
+                let actions = self.actions(r);
+                let f = self.translate(f);
+                let g = self.translate(g);
+                let c = self.variable();
+                let x = self.var(&c);
+                let step = self.all_next(&actions, &x);
+                let t = MuFormula::Bool(0, true);
+                let enabled = self.exists_next(&actions, &t);
+                let progress = self.and(step, enabled);
+                let hold = self.and(f, progress);
+                let body = self.or(g, hold);
+                MuFormula::Mu(0, c, box body)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parsers::ctl::{read_ctl_formula, to_mu_formula};
+    use algorithms::naive;
+    use utils::random::lts;
+    use bit_set::BitSet;
+
+    // 0 -a-> 1, 0 -b-> 2 and 2 -a-> 2, so 1 is a deadlock: paths that end
+    // there are maximal, and EX true fails there.
+    #[test]
+    fn maximal_paths_and_restrictions() {
+        let k = lts(&[(0, "a", 1), (0, "b", 2), (2, "a", 2)]);
+        let cases = [
+            ("EX true", vec!(0, 2)),
+            ("AX false", vec!(1)),
+            // a deadlock ends the path, so EG holds there...
+            ("EG true", vec!(0, 1, 2)),
+            ("EG [a]false", vec!(1)),
+            // ...but AF needs to reach its goal, which a deadlock does not
+            ("AF false", vec!()),
+            ("AF [a]false", vec!(1)),
+            ("A[true U [a]false]", vec!(1)),
+            ("E[<a>true U [a]false]", vec!(0, 1)),
+            ("AG <a>true", vec!(2)),
+            ("EF{a} [a]false", vec!(0, 1)),
+            ("EF{b} [a]false", vec!(1)),
+            ("EG{b} <a>true", vec!(0, 2)),
+            ("AX{b} <a>true", vec!(0, 1, 2)),
+            ("AF{b} [a]false", vec!(1))
+        ];
+        for &(formula, ref expected) in cases.iter() {
+            let ctl = read_ctl_formula(&formula.replace(" ", "")).unwrap();
+            let mu = to_mu_formula(&ctl, &k.actions());
+            assert_eq!(naive::evaluate(&k, mu.clone(), &[]).unwrap(), expected.iter().cloned().collect::<BitSet>(), "{} as {}", formula, mu.to_string());
+        }
+    }
+}
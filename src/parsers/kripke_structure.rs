@@ -13,6 +13,15 @@ pub struct MixedKripkeStructure<L: Clone+Copy> {
     pub label: HashMap<u32, HashSet<L>>
 }
 
+impl<L: Clone+Copy> MixedKripkeStructure<L> {
+    // all action labels occurring in the relations, sorted
+    pub fn actions(&self) -> Vec<String> {
+        let mut actions = self.relations.keys().map(|&(_, ref a)| a.clone()).collect::<HashSet<String>>().into_iter().collect::<Vec<String>>();
+        actions.sort();
+        actions
+    }
+}
+
 
 pub fn from_aut_to_kripke(aut: &AutFile) -> MixedKripkeStructure<()> {
     let nr_of_states = aut.header.nr_of_states;
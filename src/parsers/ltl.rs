@@ -0,0 +1,60 @@
+use std::result;
+
+// LTL over action traces: the atom `a` holds at a position of a trace when
+// the action taken at that position is `a`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum LtlFormula {
+    Bool(bool),
+    Action(String),
+    Not(Box<LtlFormula>),
+    And(Box<LtlFormula>, Box<LtlFormula>),
+    Or(Box<LtlFormula>, Box<LtlFormula>),
+    Next(Box<LtlFormula>),
+    Globally(Box<LtlFormula>),
+    Finally(Box<LtlFormula>),
+    Until(Box<LtlFormula>, Box<LtlFormula>),
+    Release(Box<LtlFormula>, Box<LtlFormula>),
+}
+
+peg! ltl_grammar(r#"
+use parsers::ltl::LtlFormula;
+
+__ = [ \t\r\n]*;
+
+false -> LtlFormula = "false" { LtlFormula::Bool(false) };
+true -> LtlFormula = "true" { LtlFormula::Bool(true) };
+action -> LtlFormula = a:$([a-z_0-9]+) { LtlFormula::Action(a.to_owned()) };
+brackets -> LtlFormula = "(" f:formula ")" { f };
+
+not -> LtlFormula = "!" __ f:unary { LtlFormula::Not(box f) };
+next -> LtlFormula = "X" __ f:unary { LtlFormula::Next(box f) };
+globally -> LtlFormula = "G" __ f:unary { LtlFormula::Globally(box f) };
+finally -> LtlFormula = "F" __ f:unary { LtlFormula::Finally(box f) };
+
+unary -> LtlFormula = not / next / globally / finally / false / true / brackets / action;
+
+until -> LtlFormula = f:unary __ "U" __ g:binary { LtlFormula::Until(box f, box g) };
+release -> LtlFormula = f:unary __ "R" __ g:binary { LtlFormula::Release(box f, box g) };
+
+binary -> LtlFormula = until / release / unary;
+
+conjunction -> LtlFormula = f:binary rest:(__ "&&" __ g:binary { g })* {
+    rest.into_iter().fold(f, |acc, g| LtlFormula::And(box acc, box g))
+};
+disjunction -> LtlFormula = f:conjunction rest:(__ "||" __ g:conjunction { g })* {
+    rest.into_iter().fold(f, |acc, g| LtlFormula::Or(box acc, box g))
+};
+
+implication -> LtlFormula = f:disjunction g:(__ "->" __ g:implication { g })? {
+    match g {
+        Some(g) => LtlFormula::Or(box LtlFormula::Not(box f), box g),
+        None => f
+    }
+};
+
+pub formula -> LtlFormula = __ f:implication __ { f };
+"#);
+
+pub fn read_ltl_formula(s: &str) -> result::Result<LtlFormula, ltl_grammar::ParseError> {
+    return ltl_grammar::formula(s);
+}
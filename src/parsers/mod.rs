@@ -2,6 +2,11 @@
 pub mod aldebaran;
 #[macro_use]
 pub mod mucalculus;
+#[macro_use]
+pub mod ctl;
+#[macro_use]
+pub mod ltl;
 
 
 pub mod kripke_structure;
+pub mod pgsolver;
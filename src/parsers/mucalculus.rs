@@ -15,6 +15,10 @@ pub enum MuFormula {
     Not(usize, Box<MuFormula>),
     DiamondOp (usize, String, Box<MuFormula>),
     BoxOp (usize, String, Box<MuFormula>),
+    // <<a>>f and [[a]]f, for steps a preceded and followed by any number of
+    // internal steps; for an internal action a, any number of internal steps
+    WeakDiamondOp (usize, String, Box<MuFormula>),
+    WeakBoxOp (usize, String, Box<MuFormula>),
     Mu(usize, String, Box<MuFormula>),
     Nu(usize, String, Box<MuFormula>),
 }
@@ -29,6 +33,8 @@ fn position(mu: &MuFormula) -> usize {
         MuFormula::Or(p, _, _) => p,
         MuFormula::DiamondOp (p, _, _) => p,
         MuFormula::BoxOp (p, _, _) => p,
+        MuFormula::WeakDiamondOp (p, _, _) => p,
+        MuFormula::WeakBoxOp (p, _, _) => p,
         MuFormula::Mu(p, _, _) => p,
         MuFormula::Nu(p, _, _) => p,
     }
@@ -37,7 +43,8 @@ fn position(mu: &MuFormula) -> usize {
 pub fn find_children(mu: &MuFormula) -> HashSet<MuFormula> {
     match *mu {
         // least fixpoint operator
-        MuFormula::Mu(_, _, ref f) | MuFormula::Nu(_, _, ref f) | MuFormula::Not(_, ref f) | MuFormula::DiamondOp (_,  _, ref f) | MuFormula::BoxOp (_,  _, ref f)  => {
+        MuFormula::Mu(_, _, ref f) | MuFormula::Nu(_, _, ref f) | MuFormula::Not(_, ref f) | MuFormula::DiamondOp (_,  _, ref f) | MuFormula::BoxOp (_,  _, ref f) |
+        MuFormula::WeakDiamondOp (_,  _, ref f) | MuFormula::WeakBoxOp (_,  _, ref f) => {
             let mut s = HashSet::new();
             s.insert(*f.clone());
             return merge_set(&find_children(f), &s);
@@ -55,6 +62,96 @@ pub fn find_children(mu: &MuFormula) -> HashSet<MuFormula> {
 }
 
 
+pub fn occurs_free(var: &str, mu: &MuFormula) -> bool {
+    match *mu {
+        MuFormula::RecursionValue(_, ref c) => c == var,
+        MuFormula::Mu(_, ref c, ref f) | MuFormula::Nu(_, ref c, ref f) => {
+            c != var && occurs_free(var, f)
+        }
+        MuFormula::Not(_, ref f) | MuFormula::DiamondOp (_,  _, ref f) | MuFormula::BoxOp (_,  _, ref f) |
+        MuFormula::WeakDiamondOp (_,  _, ref f) | MuFormula::WeakBoxOp (_,  _, ref f) => {
+            occurs_free(var, f)
+        }
+        MuFormula::And(_, ref f, ref g) | MuFormula::Or(_, ref f, ref g) => {
+            occurs_free(var, f) || occurs_free(var, g)
+        }
+        _ => false
+    }
+}
+
+// The actions of the weak modalities in the formula, without duplicates.
+pub fn weak_actions(mu: &MuFormula) -> Vec<String> {
+    let mut result = vec!();
+    collect_weak_actions(mu, &mut result);
+    result
+}
+
+fn collect_weak_actions(mu: &MuFormula, result: &mut Vec<String>) {
+    match *mu {
+        MuFormula::WeakDiamondOp (_, ref ac, ref f) | MuFormula::WeakBoxOp (_, ref ac, ref f) => {
+            if !result.contains(ac) {
+                result.push(ac.clone());
+            }
+            collect_weak_actions(f, result);
+        }
+        MuFormula::Not(_, ref f) | MuFormula::DiamondOp (_, _, ref f) | MuFormula::BoxOp (_, _, ref f) |
+        MuFormula::Mu(_, _, ref f) | MuFormula::Nu(_, _, ref f) => collect_weak_actions(f, result),
+        MuFormula::And(_, ref f, ref g) | MuFormula::Or(_, ref f, ref g) => {
+            collect_weak_actions(f, result);
+            collect_weak_actions(g, result);
+        }
+        MuFormula::Action(_, _) | MuFormula::Bool(_, _) | MuFormula::RecursionValue(_, _) => {}
+    }
+}
+
+
+// Gives every node a fresh position (in pre-order), so formulas that were
+// constructed rather than parsed can be used where positions identify nodes.
+pub fn renumber(mu: &MuFormula) -> MuFormula {
+    let mut position = 0;
+    renumber_from(mu, &mut position)
+}
+
+fn renumber_from(mu: &MuFormula, position: &mut usize) -> MuFormula {
+    let p = *position;
+    *position += 1;
+    match *mu {
+        MuFormula::Action(_, ref a) => MuFormula::Action(p, a.clone()),
+        MuFormula::Bool(_, b) => MuFormula::Bool(p, b),
+        MuFormula::RecursionValue(_, ref c) => MuFormula::RecursionValue(p, c.clone()),
+        MuFormula::Not(_, ref f) => MuFormula::Not(p, box renumber_from(f, position)),
+        MuFormula::And(_, ref f, ref g) => {
+            let f = renumber_from(f, position);
+            MuFormula::And(p, box f, box renumber_from(g, position))
+        }
+        MuFormula::Or(_, ref f, ref g) => {
+            let f = renumber_from(f, position);
+            MuFormula::Or(p, box f, box renumber_from(g, position))
+        }
+        MuFormula::DiamondOp (_, ref ac, ref f) => MuFormula::DiamondOp(p, ac.clone(), box renumber_from(f, position)),
+        MuFormula::BoxOp (_, ref ac, ref f) => MuFormula::BoxOp(p, ac.clone(), box renumber_from(f, position)),
+        MuFormula::WeakDiamondOp (_, ref ac, ref f) => MuFormula::WeakDiamondOp(p, ac.clone(), box renumber_from(f, position)),
+        MuFormula::WeakBoxOp (_, ref ac, ref f) => MuFormula::WeakBoxOp(p, ac.clone(), box renumber_from(f, position)),
+        MuFormula::Mu(_, ref c, ref f) => MuFormula::Mu(p, c.clone(), box renumber_from(f, position)),
+        MuFormula::Nu(_, ref c, ref f) => MuFormula::Nu(p, c.clone(), box renumber_from(f, position)),
+    }
+}
+
+// The subformula at the given position, if any.
+pub fn subformula(mu: &MuFormula, p: usize) -> Option<&MuFormula> {
+    if position(mu) == p {
+        return Some(mu);
+    }
+    match *mu {
+        MuFormula::Action(_, _) | MuFormula::Bool(_, _) | MuFormula::RecursionValue(_, _) => None,
+        MuFormula::Not(_, ref f) | MuFormula::DiamondOp(_, _, ref f) | MuFormula::BoxOp(_, _, ref f) |
+        MuFormula::WeakDiamondOp(_, _, ref f) | MuFormula::WeakBoxOp(_, _, ref f) |
+        MuFormula::Mu(_, _, ref f) | MuFormula::Nu(_, _, ref f) => subformula(f, p),
+        MuFormula::And(_, ref f, ref g) | MuFormula::Or(_, ref f, ref g) => subformula(f, p).or_else(|| subformula(g, p))
+    }
+}
+
+
 impl Hash for MuFormula {
     fn hash<H: Hasher>(&self, state: &mut H) {
         state.write_usize(position(self));
@@ -69,21 +166,104 @@ impl PartialEq for MuFormula {
 }
 impl Eq for MuFormula {}
 
+pub fn structurally_equal(mu: &MuFormula, other: &MuFormula) -> bool {
+    match (mu, other) {
+        (&MuFormula::Action(_, ref a), &MuFormula::Action(_, ref b)) => a == b,
+        (&MuFormula::Bool(_, a), &MuFormula::Bool(_, b)) => a == b,
+        (&MuFormula::RecursionValue(_, ref a), &MuFormula::RecursionValue(_, ref b)) => a == b,
+        (&MuFormula::Not(_, ref f), &MuFormula::Not(_, ref g)) => structurally_equal(f, g),
+        (&MuFormula::And(_, ref f1, ref g1), &MuFormula::And(_, ref f2, ref g2)) |
+        (&MuFormula::Or(_, ref f1, ref g1), &MuFormula::Or(_, ref f2, ref g2)) => {
+            structurally_equal(f1, f2) && structurally_equal(g1, g2)
+        }
+        (&MuFormula::DiamondOp(_, ref a, ref f), &MuFormula::DiamondOp(_, ref b, ref g)) |
+        (&MuFormula::BoxOp(_, ref a, ref f), &MuFormula::BoxOp(_, ref b, ref g)) |
+        (&MuFormula::WeakDiamondOp(_, ref a, ref f), &MuFormula::WeakDiamondOp(_, ref b, ref g)) |
+        (&MuFormula::WeakBoxOp(_, ref a, ref f), &MuFormula::WeakBoxOp(_, ref b, ref g)) |
+        (&MuFormula::Mu(_, ref a, ref f), &MuFormula::Mu(_, ref b, ref g)) |
+        (&MuFormula::Nu(_, ref a, ref f), &MuFormula::Nu(_, ref b, ref g)) => {
+            a == b && structurally_equal(f, g)
+        }
+        _ => false
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Syntax { Native, Mcrl2 }
+
+const OR_LEVEL: u8 = 1;
+const AND_LEVEL: u8 = 2;
+const UNARY_LEVEL: u8 = 3;
+
+// Prints a formula with the minimal amount of parentheses such that it
+// parses back to the same formula. `&&` binds stronger than `||`, both
+// associate to the left, and the body of a fixpoint extends as far to the
+// right as possible. The weak modalities skip the `internal` actions, which
+// mCRL2 has to be told explicitly.
+pub fn pretty(mu: &MuFormula, syntax: Syntax, internal: &[String]) -> String {
+    print(mu, syntax, internal, 0, true)
+}
+
+// `level` is the binding strength the surrounding operator requires, `tail`
+// tells whether nothing follows this subformula before the enclosing
+// parenthesis (or the end of the input).
+fn print(mu: &MuFormula, syntax: Syntax, internal: &[String], level: u8, tail: bool) -> String {
+    match *mu {
+        MuFormula::Action(_, ref a) => {
+            // bare actions have no mCRL2 counterpart; the evaluators treat them as the empty set
+            if syntax == Syntax::Mcrl2 { String::from("false") } else { a.clone() }
+        }
+        MuFormula::Bool(_, b) => String::from(if b { "true" } else { "false" }),
+        MuFormula::RecursionValue(_, ref c) => c.clone(),
+        MuFormula::Not(_, ref f) => format!("!{}", print(f, syntax, internal, UNARY_LEVEL, tail)),
+        MuFormula::DiamondOp (_, ref ac, ref f) => format!("<{}>{}", ac, print(f, syntax, internal, UNARY_LEVEL, tail)),
+        MuFormula::BoxOp (_, ref ac, ref f) => format!("[{}]{}", ac, print(f, syntax, internal, UNARY_LEVEL, tail)),
+        // mCRL2 writes weak modalities as regular formulas over the internal actions
+        MuFormula::WeakDiamondOp (_, ref ac, ref f) => match syntax {
+            Syntax::Native => format!("<<{}>>{}", ac, print(f, syntax, internal, UNARY_LEVEL, tail)),
+            Syntax::Mcrl2 => format!("<{}>{}", weak_regular(ac, internal), print(f, syntax, internal, UNARY_LEVEL, tail))
+        },
+        MuFormula::WeakBoxOp (_, ref ac, ref f) => match syntax {
+            Syntax::Native => format!("[[{}]]{}", ac, print(f, syntax, internal, UNARY_LEVEL, tail)),
+            Syntax::Mcrl2 => format!("[{}]{}", weak_regular(ac, internal), print(f, syntax, internal, UNARY_LEVEL, tail))
+        },
+        MuFormula::And(_, ref f, ref g) => print_binary(f, g, "&&", AND_LEVEL, syntax, internal, level, tail),
+        MuFormula::Or(_, ref f, ref g) => print_binary(f, g, "||", OR_LEVEL, syntax, internal, level, tail),
+        MuFormula::Mu(_, ref c, ref f) => print_binder("mu", c, f, syntax, internal, tail),
+        MuFormula::Nu(_, ref c, ref f) => print_binder("nu", c, f, syntax, internal, tail),
+    }
+}
+
+// (tau || i)*.a.(tau || i)*, or just the internal steps for an internal action
+fn weak_regular(ac: &String, internal: &[String]) -> String {
+    let steps = match internal.len() {
+        0 => return ac.clone(),
+        1 => format!("{}*", internal[0]),
+        _ => format!("({})*", internal.join(" || "))
+    };
+    if internal.contains(ac) { steps } else { format!("{}.{}.{}", steps, ac, steps) }
+}
+
+fn print_binary(f: &MuFormula, g: &MuFormula, op: &str, op_level: u8, syntax: Syntax, internal: &[String], level: u8, tail: bool) -> String {
+    let parens = op_level < level;
+    let s = format!("{} {} {}",
+        print(f, syntax, internal, op_level, false),
+        op,
+        print(g, syntax, internal, op_level + 1, parens || tail));
+    if parens { format!("({})", s) } else { s }
+}
+
+fn print_binder(binder: &str, c: &String, f: &MuFormula, syntax: Syntax, internal: &[String], tail: bool) -> String {
+    let s = match syntax {
+        Syntax::Native => format!("{} {}.{}", binder, c, print(f, syntax, internal, 0, true)),
+        Syntax::Mcrl2 => format!("{} {} . {}", binder, c, print(f, syntax, internal, 0, true)),
+    };
+    if tail { s } else { format!("({})", s) }
+}
+
 impl string::ToString for MuFormula {
     fn to_string(&self) -> String {
-        let s = match self {
-            &MuFormula::Action(_, ref a) => a.clone(),
-            &MuFormula::Bool(_, b) => String::from(if b { "true" } else { "false" }),
-            &MuFormula::RecursionValue(_, ref c) => c.clone(),
-            &MuFormula::Not(_, ref f) => format!("!({})", f.to_string()),
-            &MuFormula::And(_, ref f, ref g) => format!("({}&&{})", f.to_string(), g.to_string()),
-            &MuFormula::Or(_, ref f, ref g) => format!("({}||{})", f.to_string(), g.to_string()),
-            &MuFormula::DiamondOp (_, ref ac, ref f) => format!("<{}>{}",&ac,f.to_string()),
-            &MuFormula::BoxOp (_, ref ac, ref f) => format!("[{}]{}",&ac,f.to_string()),
-            &MuFormula::Mu(_, ref c, ref f) => format!("mu {}.{}",c,f.to_string()),
-            &MuFormula::Nu(_, ref c, ref f) => format!("nu {}.{}",c,f.to_string()),
-        };
-        s
+        pretty(self, Syntax::Native, &[])
     }
 }
 
@@ -96,25 +276,121 @@ impl fmt::Debug for MuFormula {
 peg! mu_grammar(r#"
 use parsers::mucalculus::MuFormula;
 
-false -> MuFormula = p:#position "false" { MuFormula::Bool(p, false) };
-true -> MuFormula = p:#position "true" { MuFormula::Bool(p, true) };
+__ = [ \t\r\n]*;
+
+// keywords only match whole words, so actions such as `falsey` are not cut
+// short, and cannot be used as actions themselves
+keyword = ("true" / "false") ![a-z_0-9];
+false -> MuFormula = p:#position "false" ![a-z_0-9] { MuFormula::Bool(p, false) };
+true -> MuFormula = p:#position "true" ![a-z_0-9] { MuFormula::Bool(p, true) };
+label -> String = !keyword a:$([a-z_0-9]+) { a.to_owned() };
 
-recursion_variable -> MuFormula = p:#position a:$([A-Z]) { MuFormula::RecursionValue(p, a.to_owned()) };
+variable_name -> String = c:$([A-Z][A-Z0-9]*) { c.to_owned() };
 
-binaryAnd -> MuFormula = p:#position "(" f:formula "&&" g:formula ")" { MuFormula::And(p, box f, box g) };
-binaryOr -> MuFormula = p:#position "(" f:formula "||" g:formula ")" { MuFormula::Or(p, box f, box g) };
+recursion_variable -> MuFormula = p:#position a:variable_name { MuFormula::RecursionValue(p, a) };
 
-action -> MuFormula = p:#position a:$([a-z_]+) { MuFormula::Action(p, a.to_owned()) };
+action -> MuFormula = p:#position !keyword a:$([a-z_]+) { MuFormula::Action(p, a.to_owned()) };
 
-diamond -> MuFormula = p:#position "<" a:$([a-z_0-9]+) ">" f:formula { MuFormula::DiamondOp(p, a.to_owned(), box f) };
-box -> MuFormula = p:#position "[" a:$([a-z_0-9]+) "]" f:formula { MuFormula::BoxOp(p, a.to_owned(), box f) };
+brackets -> MuFormula = "(" f:formula ")" { f };
 
-mu_point -> MuFormula = p:#position 'mu' c:$([A-Z]) '.' f:formula { MuFormula::Mu(p, c.to_owned(), box f) };
-nu_point -> MuFormula = p:#position 'nu' c:$([A-Z]) '.' f:formula { MuFormula::Nu(p, c.to_owned(), box f) };
+not -> MuFormula = p:#position "!" __ f:unary { MuFormula::Not(p, box f) };
+diamond -> MuFormula = p:#position "<" a:label ">" __ f:unary { MuFormula::DiamondOp(p, a, box f) };
+box -> MuFormula = p:#position "[" a:label "]" __ f:unary { MuFormula::BoxOp(p, a, box f) };
+weak_diamond -> MuFormula = p:#position "<<" a:label ">>" __ f:unary { MuFormula::WeakDiamondOp(p, a, box f) };
+weak_box -> MuFormula = p:#position "[[" a:label "]]" __ f:unary { MuFormula::WeakBoxOp(p, a, box f) };
 
-pub formula -> MuFormula = false / true / recursion_variable / binaryAnd / binaryOr / diamond / box / mu_point / nu_point;
+mu_point -> MuFormula = p:#position 'mu' __ c:variable_name __ '.' f:formula { MuFormula::Mu(p, c, box f) };
+nu_point -> MuFormula = p:#position 'nu' __ c:variable_name __ '.' f:formula { MuFormula::Nu(p, c, box f) };
+
+unary -> MuFormula = not / weak_diamond / weak_box / diamond / box / mu_point / nu_point / false / true / recursion_variable / brackets / action;
+
+conjunction -> MuFormula = f:unary rest:(__ p:#position "&&" __ g:unary { (p, g) })* {
+    rest.into_iter().fold(f, |acc, (p, g)| MuFormula::And(p, box acc, box g))
+};
+disjunction -> MuFormula = f:conjunction rest:(__ p:#position "||" __ g:conjunction { (p, g) })* {
+    rest.into_iter().fold(f, |acc, (p, g)| MuFormula::Or(p, box acc, box g))
+};
+
+pub formula -> MuFormula = __ f:disjunction __ { f };
 "#);
 
 pub fn read_mu_formula(s: &str) -> result::Result<MuFormula, mu_grammar::ParseError> {
     return mu_grammar::formula(s);
 }
+
+// Whether printing and parsing gives the formula back. mCRL2 has no bare
+// actions, and without internal actions its weak modalities are ordinary
+// ones, so there the formula is compared with its mCRL2 reading.
+pub fn round_trips(mu: &MuFormula, syntax: Syntax) -> bool {
+    let expected = match syntax {
+        Syntax::Native => mu.clone(),
+        Syntax::Mcrl2 => mcrl2_reading(mu)
+    };
+    match read_mu_formula(pretty(mu, syntax, &[]).as_str()) {
+        Ok(parsed) => structurally_equal(&expected, &parsed),
+        Err(_) => false
+    }
+}
+
+// the formula with bare actions replaced by false and weak modalities by
+// ordinary ones, as it is printed for mCRL2 without internal actions
+fn mcrl2_reading(mu: &MuFormula) -> MuFormula {
+    match *mu {
+        MuFormula::Action(p, _) => MuFormula::Bool(p, false),
+        MuFormula::Bool(_, _) | MuFormula::RecursionValue(_, _) => mu.clone(),
+        MuFormula::Not(p, ref f) => MuFormula::Not(p, box mcrl2_reading(f)),
+        MuFormula::And(p, ref f, ref g) => MuFormula::And(p, box mcrl2_reading(f), box mcrl2_reading(g)),
+        MuFormula::Or(p, ref f, ref g) => MuFormula::Or(p, box mcrl2_reading(f), box mcrl2_reading(g)),
+        MuFormula::DiamondOp(p, ref ac, ref f) | MuFormula::WeakDiamondOp(p, ref ac, ref f) => MuFormula::DiamondOp(p, ac.clone(), box mcrl2_reading(f)),
+        MuFormula::BoxOp(p, ref ac, ref f) | MuFormula::WeakBoxOp(p, ref ac, ref f) => MuFormula::BoxOp(p, ac.clone(), box mcrl2_reading(f)),
+        MuFormula::Mu(p, ref c, ref f) => MuFormula::Mu(p, c.clone(), box mcrl2_reading(f)),
+        MuFormula::Nu(p, ref c, ref f) => MuFormula::Nu(p, c.clone(), box mcrl2_reading(f)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parsers::mucalculus::{MuFormula, read_mu_formula, pretty, round_trips, structurally_equal, Syntax};
+    use utils::random::{Random, random_formula};
+
+    #[test]
+    fn random_formulas_round_trip() {
+        let actions = vec!(String::from("a"), String::from("b"), String::from("tau"));
+        for seed in 1..11 {
+            let mut rng = Random::new(seed);
+            for _ in 0..100 {
+                let mu = random_formula(&mut rng, 6, &actions, true);
+                assert!(round_trips(&mu, Syntax::Native), "does not round trip: {}", pretty(&mu, Syntax::Native, &[]));
+                assert!(round_trips(&mu, Syntax::Mcrl2), "does not round trip: {}", pretty(&mu, Syntax::Mcrl2, &[]));
+            }
+        }
+    }
+
+    #[test]
+    fn mcrl2_syntax() {
+        let mu = read_mu_formula("nu X.(<a>X && mu Y.[[b]]Y || c)").unwrap();
+        assert_eq!(pretty(&mu, Syntax::Mcrl2, &[]), "nu X . <a>X && mu Y . [b]Y || false");
+        let internal = vec!(String::from("tau"), String::from("i"));
+        assert_eq!(pretty(&mu, Syntax::Mcrl2, &internal), "nu X . <a>X && mu Y . [(tau || i)*.b.(tau || i)*]Y || false");
+        assert_eq!(pretty(&read_mu_formula("<<tau>>true").unwrap(), Syntax::Mcrl2, &internal[..1]), "<tau*>true");
+    }
+
+    // true and false are keywords, also inside modalities
+    #[test]
+    fn keywords_are_not_actions() {
+        assert!(structurally_equal(&read_mu_formula("true").unwrap(), &MuFormula::Bool(0, true)));
+        assert!(read_mu_formula("<true>false").is_err());
+        assert!(read_mu_formula("[[false]]true").is_err());
+        assert!(read_mu_formula("<truer>false").is_ok());
+    }
+
+    #[test]
+    fn actions_with_keyword_prefixes_round_trip() {
+        for name in &["trueval", "falsey", "true_", "mux", "mu", "nu"] {
+            let mu = MuFormula::And(0, box MuFormula::Action(1, name.to_string()), box MuFormula::Bool(2, true));
+            assert!(round_trips(&mu, Syntax::Native), "does not round trip: {}", pretty(&mu, Syntax::Native, &[]));
+            let parsed = read_mu_formula(name).unwrap();
+            assert!(structurally_equal(&parsed, &MuFormula::Action(0, name.to_string())), "{} parsed as {}", name, parsed.to_string());
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use algorithms::parity_game::{ParityGame, Player, Solution, TRUE_VERTEX, FALSE_VERTEX};
+use std::io::{BufRead, Write};
+use std::io;
+use std::str::FromStr;
+
+#[derive(Debug)]
+pub enum SolutionErrors {
+    Io(io::Error),
+    Syntax(usize, String),
+    UnknownVertex(usize),
+    Missing(usize)
+}
+
+fn owner_id(player: Player) -> usize {
+    match player {
+        Player::Even => 0,
+        Player::Odd => 1
+    }
+}
+
+// Writes the game in PGSolver's .gm format. Both use max-parity, so
+// priorities and owners are written as they are; vertices are named after
+// the state and the position of their subformula.
+pub fn write_game<W: Write>(game: &ParityGame, out: &mut W) -> io::Result<()> {
+    try!(writeln!(out, "parity {};", game.len() - 1));
+    for v in 0..game.len() {
+        let successors = game.successors[v].iter().map(|w| w.to_string()).collect::<Vec<String>>();
+        let name = match game.labels[v] {
+            Some((s, position)) => format!("{}:{}", s, position),
+            None => String::from(if v == TRUE_VERTEX { "true" } else { "false" })
+        };
+        try!(writeln!(out, "{} {} {} {} \"{}\";", v, game.priority[v], owner_id(game.owner[v]), successors.join(","), name));
+    }
+    Ok(())
+}
+
+// Reads a solution in PGSolver's format, lines `vertex winner [successor];`
+// after an optional `paritysol n;` header, for the game it was exported from.
+pub fn read_solution<R: BufRead>(game: &ParityGame, input: R) -> Result<Solution, SolutionErrors> {
+    let mut winner: Vec<Option<Player>> = vec![None; game.len()];
+    // the sinks are decided anyway, so they may be left out
+    winner[TRUE_VERTEX] = Some(Player::Even);
+    winner[FALSE_VERTEX] = Some(Player::Odd);
+    let mut strategy = vec![None; game.len()];
+    for (i, line) in input.lines().enumerate() {
+        let line = try!(line.map_err(SolutionErrors::Io));
+        let line = line.trim().trim_right_matches(';');
+        if line.is_empty() || line.starts_with("paritysol") {
+            continue;
+        }
+        let syntax = || SolutionErrors::Syntax(i + 1, String::from(line));
+        let fields = try!(line.split_whitespace()
+            .map(|f| usize::from_str(f))
+            .collect::<Result<Vec<usize>, _>>()
+            .map_err(|_| syntax()));
+        if fields.len() < 2 || fields.len() > 3 || fields[1] > 1 {
+            return Err(syntax());
+        }
+        let v = fields[0];
+        if v >= game.len() {
+            return Err(SolutionErrors::UnknownVertex(v));
+        }
+        winner[v] = Some(if fields[1] == 0 { Player::Even } else { Player::Odd });
+        if fields.len() == 3 {
+            if !game.successors[v].contains(&fields[2]) {
+                return Err(syntax());
+            }
+            strategy[v] = Some(fields[2]);
+        }
+    }
+    let mut result = vec!();
+    for (v, w) in winner.into_iter().enumerate() {
+        match w {
+            Some(w) => result.push(w),
+            None => return Err(SolutionErrors::Missing(v))
+        }
+    }
+    Ok(Solution { winner: result, strategy: strategy })
+}
+
+#[cfg(test)]
+mod tests {
+    use algorithms::parity_game::{build, Player, TRUE_VERTEX, FALSE_VERTEX};
+    use parsers::mucalculus::read_mu_formula;
+    use parsers::pgsolver::{write_game, read_solution, SolutionErrors};
+    use utils::random::lts;
+    use std::io::Cursor;
+
+    #[test]
+    fn round_trip() {
+        // <a>true holds in 0, which can move to 1, and not in 1
+        let k = lts(&[(0, "a", 1)]);
+        let game = build(&k, &read_mu_formula("<a>true").unwrap(), &[]).unwrap();
+        let mut out = vec!();
+        write_game(&game, &mut out).unwrap();
+        let written = String::from_utf8(out).unwrap();
+        let lines = written.lines().collect::<Vec<&str>>();
+        assert_eq!(lines[0], format!("parity {};", game.len() - 1));
+        let start = game.vertices[&(0, 0)];
+        let stuck = game.vertices[&(1, 0)];
+        let next = game.vertices[&(1, 1)];
+        assert_eq!(lines[1 + TRUE_VERTEX], format!("{} 0 0 {} \"true\";", TRUE_VERTEX, TRUE_VERTEX));
+        assert_eq!(lines[1 + start], format!("{} 0 0 {} \"0:0\";", start, next));
+        assert_eq!(lines[1 + stuck], format!("{} 0 0 {} \"1:0\";", stuck, FALSE_VERTEX));
+
+        let solution = format!("paritysol {};\n{} 0 {};\n{} 1;\n{} 0 {};\n", game.len() - 1, start, next, stuck, next, TRUE_VERTEX);
+        let solution = read_solution(&game, Cursor::new(solution)).unwrap();
+        assert_eq!(solution.winner[start], Player::Even);
+        assert_eq!(solution.winner[stuck], Player::Odd);
+        assert_eq!(solution.winner[FALSE_VERTEX], Player::Odd);
+        assert_eq!(solution.strategy[start], Some(next));
+        assert_eq!(solution.strategy[next], Some(TRUE_VERTEX));
+        assert_eq!(solution.strategy[stuck], None);
+        assert_eq!(game.satisfying_states(&solution).iter().collect::<Vec<usize>>(), vec!(0));
+    }
+
+    #[test]
+    fn rejects_solutions_for_other_games() {
+        let k = lts(&[(0, "a", 1)]);
+        let game = build(&k, &read_mu_formula("<a>true").unwrap(), &[]).unwrap();
+        let all = (2..game.len()).map(|v| format!("{} 0;", v)).collect::<Vec<String>>().join("\n");
+        assert!(read_solution(&game, Cursor::new(all.clone())).is_ok());
+        match read_solution(&game, Cursor::new(format!("{}\n{} 0;", all, game.len()))) {
+            Err(SolutionErrors::UnknownVertex(v)) => assert_eq!(v, game.len()),
+            _ => panic!("accepted an unknown vertex")
+        }
+        match read_solution(&game, Cursor::new("2 0;")) {
+            Err(SolutionErrors::Missing(_)) => {}
+            _ => panic!("accepted a partial solution")
+        }
+        match read_solution(&game, Cursor::new(format!("{}\n2 0 2;", all))) {
+            Err(SolutionErrors::Syntax(line, _)) => assert_eq!(line, game.len() - 1),
+            _ => panic!("accepted a move that is not an edge")
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use std::cmp;
+
+const UNVISITED: usize = ::std::usize::MAX;
+
+// Tarjan's algorithm without recursion, so deep graphs do not overflow the
+// stack. Vertices are 0..successors.len(); components are returned in
+// reverse topological order (a component only reaches earlier ones).
+pub fn strongly_connected_components(successors: &Vec<Vec<usize>>) -> Vec<Vec<usize>> {
+    let n = successors.len();
+    let mut index = vec![UNVISITED; n];
+    let mut lowlink = vec![0; n];
+    let mut on_stack = vec![false; n];
+    let mut stack = vec!();
+    let mut components = vec!();
+    let mut counter = 0;
+
+    for root in 0..n {
+        if index[root] != UNVISITED {
+            continue;
+        }
+        index[root] = counter;
+        lowlink[root] = counter;
+        counter += 1;
+        stack.push(root);
+        on_stack[root] = true;
+        let mut call = vec![(root, 0)];
+
+        loop {
+            let (v, i) = match call.last() {
+                Some(&(v, i)) => (v, i),
+                None => break
+            };
+            if i < successors[v].len() {
+                let top = call.len() - 1;
+                call[top].1 += 1;
+                let w = successors[v][i];
+                if index[w] == UNVISITED {
+                    index[w] = counter;
+                    lowlink[w] = counter;
+                    counter += 1;
+                    stack.push(w);
+                    on_stack[w] = true;
+                    call.push((w, 0));
+                } else if on_stack[w] {
+                    lowlink[v] = cmp::min(lowlink[v], index[w]);
+                }
+            } else {
+                call.pop();
+                if let Some(&(u, _)) = call.last() {
+                    lowlink[u] = cmp::min(lowlink[u], lowlink[v]);
+                }
+                if lowlink[v] == index[v] {
+                    let mut component = vec!();
+                    loop {
+                        let w = stack.pop().unwrap();
+                        on_stack[w] = false;
+                        component.push(w);
+                        if w == v {
+                            break;
+                        }
+                    }
+                    components.push(component);
+                }
+            }
+        }
+    }
+    components
+}
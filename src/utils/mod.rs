@@ -1 +1,4 @@
-pub mod collections;
\ No newline at end of file
+pub mod collections;
+#[cfg(test)]
+pub mod random;
+pub mod graph;
\ No newline at end of file
@@ -0,0 +1,129 @@
+use parsers::mucalculus::MuFormula;
+use parsers::aldebaran::{AutFile, AutHeader, AutEdge};
+use parsers::kripke_structure::{MixedKripkeStructure, from_aut_to_kripke};
+
+// xorshift64* generator; good enough for generating test inputs and keeps
+// runs reproducible from a seed.
+pub struct Random {
+    state: u64
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Random {
+        Random { state: if seed == 0 { 0x9E3779B97F4A7C15 } else { seed } }
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545F4914F6CDD1D)
+    }
+
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next() % (n as u64)) as usize
+    }
+}
+
+const VARIABLES: &'static str = "XYZWVU";
+
+// Generates a closed formula of at most the given depth. Nested binders get
+// distinct names, so no binder shadows another and the formula is also
+// accepted by the naive evaluator; sibling binders may reuse a name.
+pub fn random_formula(rng: &mut Random, depth: usize, actions: &[String], allow_negation: bool) -> MuFormula {
+    let mut position = 0;
+    let mut bound = vec!();
+    gen_formula(rng, depth, actions, allow_negation, &mut bound, &mut position)
+}
+
+fn gen_formula(rng: &mut Random, depth: usize, actions: &[String], allow_negation: bool, bound: &mut Vec<String>, position: &mut usize) -> MuFormula {
+    *position += 1;
+    let p = *position;
+    if depth == 0 {
+        if bound.len() > 0 && rng.below(3) > 0 {
+            return MuFormula::RecursionValue(p, bound[rng.below(bound.len())].clone());
+        }
+        if rng.below(4) == 0 {
+            return MuFormula::Action(p, actions[rng.below(actions.len())].clone());
+        }
+        return MuFormula::Bool(p, rng.below(2) == 0);
+    }
+    let fresh = bound.len() < VARIABLES.len();
+    match rng.below(9) {
+        0 => {
+            let f = gen_formula(rng, depth - 1, actions, allow_negation, bound, position);
+            let g = gen_formula(rng, depth - 1, actions, allow_negation, bound, position);
+            MuFormula::And(p, box f, box g)
+        }
+        1 => {
+            let f = gen_formula(rng, depth - 1, actions, allow_negation, bound, position);
+            let g = gen_formula(rng, depth - 1, actions, allow_negation, bound, position);
+            MuFormula::Or(p, box f, box g)
+        }
+        2 => {
+            let ac = actions[rng.below(actions.len())].clone();
+            MuFormula::DiamondOp(p, ac, box gen_formula(rng, depth - 1, actions, allow_negation, bound, position))
+        }
+        3 => {
+            let ac = actions[rng.below(actions.len())].clone();
+            MuFormula::BoxOp(p, ac, box gen_formula(rng, depth - 1, actions, allow_negation, bound, position))
+        }
+        7 => {
+            let ac = actions[rng.below(actions.len())].clone();
+            MuFormula::WeakDiamondOp(p, ac, box gen_formula(rng, depth - 1, actions, allow_negation, bound, position))
+        }
+        8 => {
+            let ac = actions[rng.below(actions.len())].clone();
+            MuFormula::WeakBoxOp(p, ac, box gen_formula(rng, depth - 1, actions, allow_negation, bound, position))
+        }
+        4 if allow_negation && bound.len() == 0 => {
+            // negating a closed formula keeps every fixpoint monotone
+            MuFormula::Not(p, box gen_formula(rng, depth - 1, actions, allow_negation, bound, position))
+        }
+        5 | 6 if fresh => {
+            let c = VARIABLES[bound.len()..bound.len() + 1].to_owned();
+            bound.push(c.clone());
+            let f = gen_formula(rng, depth - 1, actions, allow_negation, bound, position);
+            bound.pop();
+            if rng.below(2) == 0 {
+                MuFormula::Mu(p, c, box f)
+            } else {
+                MuFormula::Nu(p, c, box f)
+            }
+        }
+        _ => {
+            let ac = actions[rng.below(actions.len())].clone();
+            MuFormula::DiamondOp(p, ac, box gen_formula(rng, depth - 1, actions, allow_negation, bound, position))
+        }
+    }
+}
+
+// Generates an LTS with the given number of states and transitions, with
+// labels drawn from `actions` and state 0 as initial state.
+pub fn random_kripke(rng: &mut Random, states: usize, transitions: usize, actions: &[String]) -> MixedKripkeStructure<()> {
+    let mut edges = vec!();
+    for _ in 0..transitions {
+        edges.push(AutEdge {
+            start_state: rng.below(states) as u64,
+            label: actions[rng.below(actions.len())].clone(),
+            end_state: rng.below(states) as u64
+        });
+    }
+    from_edges(states, edges)
+}
+
+// The LTS with the given transitions and state 0 as initial state, for
+// known-answer tests.
+pub fn lts(transitions: &[(u64, &str, u64)]) -> MixedKripkeStructure<()> {
+    let edges = transitions.iter().map(|&(s, a, t)| AutEdge { start_state: s, label: a.to_owned(), end_state: t }).collect::<Vec<AutEdge>>();
+    let states = edges.iter().map(|e| 1 + e.start_state.max(e.end_state) as usize).max().unwrap_or(1);
+    from_edges(states, edges)
+}
+
+fn from_edges(states: usize, edges: Vec<AutEdge>) -> MixedKripkeStructure<()> {
+    let aut = AutFile {
+        header: AutHeader { first_state: 0, nr_of_transitions: edges.len(), nr_of_states: states },
+        edges: edges
+    };
+    from_aut_to_kripke(&aut)
+}